@@ -1,13 +1,13 @@
+use argon2::Error as ArgonError;
+use reqwest::Error as ReqwestError;
+use reqwest_middleware::Error as MiddlewareReqwestError;
 use std::fmt::Formatter;
+use tracing::{event, instrument, Level};
 use warp::body::BodyDeserializeError;
 use warp::cors::CorsForbidden;
 use warp::http::StatusCode;
 use warp::reject::Reject;
 use warp::{Rejection, Reply};
-use tracing::{event, Level, instrument};
-use argon2::Error as ArgonError;
-use reqwest::Error as ReqwestError;
-use reqwest_middleware::Error as MiddlewareReqwestError;
 
 #[derive(Debug, Clone)]
 pub struct APILayerError {
@@ -19,9 +19,36 @@ pub struct APILayerError {
 pub enum Error {
     ParseError(std::num::ParseIntError),
     MissingParameters,
+    // A query parameter is there, but its value cannot be used
+    InvalidParameter(String),
     WrongPassword,
+    // The `Authorization` header is missing on a protected route
+    Unauthorized,
+    // The token could not be decrypted, is expired or not yet valid
+    CannotDecryptToken,
+    // The authenticated account is not allowed to change the resource
+    Forbidden,
+    // The content contains words the moderation policy does not allow
+    BadWordsFound(Vec<String>),
+    // The moderation API failed or its circuit breaker is open
+    ModerationUnavailable,
     ArgonLibraryError(ArgonError),
     QuestionNotFound,
+    AnswerNotFound,
+    // The answer exists, but belongs to another question than the one it was given for
+    AnswerOfAnotherQuestion,
+    CommentNotFound,
+    // A comment is empty or too long
+    InvalidComment(String),
+    AccountNotFound,
+    // An account with the e-mail address exists already, the database reports this as a duplicate key
+    AccountAlreadyExists,
+    // The account was disabled by an admin and cannot log in
+    AccountDisabled,
+    // A tag of a question cannot be stored, e.g. because it is too long
+    InvalidTag(String),
+    // An import could not be read as a whole, single rows which are wrong do not fail it
+    InvalidImport(String),
     DatabaseQueryError(sqlx::Error),
     ExternalAPIError(ReqwestError),
     // In case the HTTP client(Reqwest) returns an error
@@ -47,24 +74,49 @@ impl std::fmt::Display for Error {
                 write!(f, "Cannot parse parameter: {}", err)
             }
             Error::MissingParameters => write!(f, "Missing parameter"),
+            Error::InvalidParameter(reason) => write!(f, "Invalid parameter: {}", reason),
             Error::WrongPassword => write!(f, "Wrong password"),
+            Error::Unauthorized => write!(f, "No authorization token provided"),
+            Error::CannotDecryptToken => write!(f, "Cannot decrypt token"),
+            Error::Forbidden => write!(f, "No permission to change the underlying resource"),
+            Error::ModerationUnavailable => {
+                write!(f, "Content moderation is not available, try again later")
+            }
+            Error::BadWordsFound(words) => {
+                write!(
+                    f,
+                    "Content contains words which are not allowed: {}",
+                    words.join(", ")
+                )
+            }
             Error::ArgonLibraryError(_) => write!(f, "Cannot verifiy password"),
             Error::QuestionNotFound => write!(f, "Question not found"),
+            Error::AnswerNotFound => write!(f, "Answer not found"),
+            Error::AnswerOfAnotherQuestion => {
+                write!(f, "The answer belongs to another question")
+            }
+            Error::CommentNotFound => write!(f, "Comment not found"),
+            Error::InvalidComment(reason) => write!(f, "Invalid comment: {}", reason),
+            Error::AccountNotFound => write!(f, "Account not found"),
+            Error::AccountAlreadyExists => write!(f, "Account already exists"),
+            Error::AccountDisabled => write!(f, "Account is disabled"),
+            Error::InvalidTag(reason) => write!(f, "Invalid tag: {}", reason),
+            Error::InvalidImport(reason) => write!(f, "Cannot import: {}", reason),
             Error::DatabaseQueryError(_) => {
                 write!(f, "Query could not be executed")
-            },
+            }
             Error::ExternalAPIError(err) => {
                 write!(f, "Cannot execute: {}", err)
-            },
+            }
             Error::ClientError(err) => {
                 write!(f, "External Client error: {}", err)
-            },
+            }
             Error::ServerError(err) => {
                 write!(f, "External Server error: {}", err)
-            },
+            }
             Error::ReqwestAPIError(err) => {
                 write!(f, "External API error: {}", err)
-            },
+            }
             Error::MiddlewareReqwestAPIError(err) => {
                 write!(f, "External API error: {}", err)
             }
@@ -72,11 +124,10 @@ impl std::fmt::Display for Error {
     }
 }
 
+impl std::error::Error for Error {}
 impl Reject for Error {}
 impl Reject for APILayerError {}
 
-const DUPLICATE_KEY: u32 = 23505;
-
 #[instrument]
 pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
     if let Some(crate::Error::DatabaseQueryError(e)) = r.find() {
@@ -84,8 +135,7 @@ pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
 
         match e {
             sqlx::Error::Database(err) => {
-                if err.code().unwrap().parse::<u32>().unwrap() ==
-                    DUPLICATE_KEY {
+                if err.is_unique_violation() {
                     Ok(warp::reply::with_status(
                         "Account already exists".to_string(),
                         StatusCode::UNPROCESSABLE_ENTITY,
@@ -96,13 +146,11 @@ pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
                         StatusCode::UNPROCESSABLE_ENTITY,
                     ))
                 }
-            },
-            _ => {
-                Ok(warp::reply::with_status(
-                    "Cannot update data".to_string(),
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                ))
             }
+            _ => Ok(warp::reply::with_status(
+                "Cannot update data".to_string(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )),
         }
     } else if let Some(crate::Error::ExternalAPIError(e)) = r.find() {
         event!(Level::ERROR, "{}", e);
@@ -120,6 +168,55 @@ pub async fn return_error(r: Rejection) -> Result<impl Reply, Rejection> {
             error.to_string(),
             StatusCode::UNPROCESSABLE_ENTITY,
         ))
+    } else if let Some(error @ (crate::Error::Unauthorized | crate::Error::CannotDecryptToken)) =
+        r.find()
+    {
+        event!(Level::ERROR, "{}", error);
+        Ok(warp::reply::with_status(
+            "No permission to access the underlying resource".to_string(),
+            StatusCode::UNAUTHORIZED,
+        ))
+    } else if let Some(error @ crate::Error::ModerationUnavailable) = r.find() {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else if let Some(error @ crate::Error::AccountDisabled) = r.find() {
+        event!(Level::WARN, "{}", error);
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(crate::Error::Forbidden) = r.find() {
+        event!(Level::ERROR, "Not matching account id");
+        Ok(warp::reply::with_status(
+            "No permission to change the underlying resource".to_string(),
+            StatusCode::FORBIDDEN,
+        ))
+    } else if let Some(
+        error @ (crate::Error::QuestionNotFound
+        | crate::Error::AnswerNotFound
+        | crate::Error::CommentNotFound
+        | crate::Error::AccountNotFound),
+    ) = r.find()
+    {
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::NOT_FOUND,
+        ))
+    } else if let Some(
+        error @ (crate::Error::InvalidImport(_)
+        | crate::Error::AnswerOfAnotherQuestion
+        | crate::Error::InvalidComment(_)
+        | crate::Error::InvalidParameter(_)
+        | crate::Error::InvalidTag(_)),
+    ) = r.find()
+    {
+        Ok(warp::reply::with_status(
+            error.to_string(),
+            StatusCode::BAD_REQUEST,
+        ))
     } else if let Some(error) = r.find::<Error>() {
         event!(Level::ERROR, "{}", error);
         Ok(warp::reply::with_status(
@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS accounts (
+    id serial PRIMARY KEY,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    password VARCHAR(255) NOT NULL
+);
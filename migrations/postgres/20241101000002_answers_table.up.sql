@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS answers (
+    id serial PRIMARY KEY,
+    content TEXT NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    question_id integer NOT NULL REFERENCES questions ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+DROP INDEX IF EXISTS answers_status;
+DROP INDEX IF EXISTS questions_status;
+
+ALTER TABLE answers DROP COLUMN IF EXISTS status;
+ALTER TABLE answers DROP COLUMN IF EXISTS account_id;
+
+ALTER TABLE questions DROP COLUMN IF EXISTS status;
+ALTER TABLE questions DROP COLUMN IF EXISTS account_id;
+
+ALTER TABLE accounts DROP COLUMN IF EXISTS role;
@@ -0,0 +1,12 @@
+-- Databases created before the migrations were checked in may have the tables
+-- already, the columns get added to them as well
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS role VARCHAR(16) NOT NULL DEFAULT 'user';
+
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS account_id integer REFERENCES accounts;
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS status VARCHAR(16) NOT NULL DEFAULT 'published';
+
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS account_id integer REFERENCES accounts;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS status VARCHAR(16) NOT NULL DEFAULT 'published';
+
+CREATE INDEX IF NOT EXISTS questions_status ON questions (status);
+CREATE INDEX IF NOT EXISTS answers_status ON answers (status);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS questions_created_on;
+DROP INDEX IF EXISTS answers_search;
+DROP INDEX IF EXISTS questions_search;
+
+ALTER TABLE answers DROP COLUMN IF EXISTS search;
+ALTER TABLE questions DROP COLUMN IF EXISTS search;
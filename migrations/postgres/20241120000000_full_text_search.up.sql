@@ -0,0 +1,12 @@
+-- Titles weigh more than the text of a question, answers get a vector of their own
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS search tsvector GENERATED ALWAYS AS (
+    setweight(to_tsvector('english', title), 'A') ||
+    setweight(to_tsvector('english', content), 'B')
+) STORED;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS search tsvector GENERATED ALWAYS AS (
+    to_tsvector('english', content)
+) STORED;
+
+CREATE INDEX IF NOT EXISTS questions_search ON questions USING GIN (search);
+CREATE INDEX IF NOT EXISTS answers_search ON answers USING GIN (search);
+CREATE INDEX IF NOT EXISTS questions_created_on ON questions (created_on);
@@ -0,0 +1,2 @@
+-- The tags stay normalized
+DROP INDEX IF EXISTS questions_tags;
@@ -0,0 +1,13 @@
+-- Tags are stored trimmed, lowercase and once per question from now on
+UPDATE questions SET tags = (
+    SELECT array_agg(tag ORDER BY position)
+    FROM (
+        SELECT lower(btrim(tag)) AS tag, min(position) AS position
+        FROM unnest(questions.tags) WITH ORDINALITY AS listed (tag, position)
+        WHERE btrim(tag) <> ''
+        GROUP BY lower(btrim(tag))
+    ) AS normalized
+)
+WHERE tags IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS questions_tags ON questions USING GIN (tags);
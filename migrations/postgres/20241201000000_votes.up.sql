@@ -0,0 +1,19 @@
+-- Every account has at most one vote on a question or answer, its score is the sum of them
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS score integer NOT NULL DEFAULT 0;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS score integer NOT NULL DEFAULT 0;
+
+CREATE TABLE IF NOT EXISTS question_votes (
+    question_id integer NOT NULL REFERENCES questions ON DELETE CASCADE,
+    account_id integer NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    value smallint NOT NULL CHECK (value IN (-1, 1)),
+    PRIMARY KEY (question_id, account_id)
+);
+
+CREATE TABLE IF NOT EXISTS answer_votes (
+    answer_id integer NOT NULL REFERENCES answers ON DELETE CASCADE,
+    account_id integer NOT NULL REFERENCES accounts ON DELETE CASCADE,
+    value smallint NOT NULL CHECK (value IN (-1, 1)),
+    PRIMARY KEY (answer_id, account_id)
+);
+
+CREATE INDEX IF NOT EXISTS questions_score ON questions (score);
@@ -0,0 +1,3 @@
+-- The answer the author of a question accepted, if any
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS accepted_answer_id integer
+    REFERENCES answers ON DELETE SET NULL;
@@ -0,0 +1,18 @@
+-- Short remarks on a question or an answer, the row they are on counts the visible ones
+CREATE TABLE IF NOT EXISTS comments (
+    id serial PRIMARY KEY,
+    content TEXT NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    question_id integer REFERENCES questions ON DELETE CASCADE,
+    answer_id integer REFERENCES answers ON DELETE CASCADE,
+    account_id integer REFERENCES accounts,
+    status VARCHAR(16) NOT NULL DEFAULT 'published',
+    CHECK ((question_id IS NULL) <> (answer_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS comments_question_id ON comments (question_id);
+CREATE INDEX IF NOT EXISTS comments_answer_id ON comments (answer_id);
+CREATE INDEX IF NOT EXISTS comments_status ON comments (status);
+
+ALTER TABLE questions ADD COLUMN IF NOT EXISTS comment_count integer NOT NULL DEFAULT 0;
+ALTER TABLE answers ADD COLUMN IF NOT EXISTS comment_count integer NOT NULL DEFAULT 0;
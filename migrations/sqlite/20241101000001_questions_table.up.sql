@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS questions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    title TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    account_id INTEGER REFERENCES accounts (id),
+    status TEXT NOT NULL DEFAULT 'published'
+);
+
+-- SQLite has no arrays, every tag is a row, in the order the question lists them
+CREATE TABLE IF NOT EXISTS question_tags (
+    question_id INTEGER NOT NULL REFERENCES questions (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (question_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS question_tags_tag ON question_tags (tag);
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS answers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    content TEXT NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    question_id INTEGER NOT NULL REFERENCES questions (id) ON DELETE CASCADE,
+    account_id INTEGER REFERENCES accounts (id),
+    status TEXT NOT NULL DEFAULT 'published'
+);
@@ -0,0 +1 @@
+ALTER TABLE accounts ADD COLUMN disabled BOOLEAN NOT NULL DEFAULT false;
@@ -0,0 +1,2 @@
+-- Searches filter by date, the text itself gets matched without an index
+CREATE INDEX IF NOT EXISTS questions_created_on ON questions (created_on);
@@ -0,0 +1,2 @@
+-- The tags stay normalized, there is nothing to undo
+SELECT 1;
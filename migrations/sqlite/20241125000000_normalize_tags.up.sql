@@ -0,0 +1,10 @@
+-- Tags are stored trimmed, lowercase and once per question from now on,
+-- `question_tags_tag` already indexes them
+UPDATE question_tags SET tag = lower(trim(tag));
+DELETE FROM question_tags WHERE tag = '';
+DELETE FROM question_tags WHERE EXISTS (
+    SELECT 1 FROM question_tags AS earlier
+    WHERE earlier.question_id = question_tags.question_id
+        AND earlier.tag = question_tags.tag
+        AND earlier.position < question_tags.position
+);
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS questions_score;
+DROP TABLE IF EXISTS answer_votes;
+DROP TABLE IF EXISTS question_votes;
+
+ALTER TABLE answers DROP COLUMN score;
+ALTER TABLE questions DROP COLUMN score;
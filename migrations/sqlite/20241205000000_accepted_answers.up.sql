@@ -0,0 +1,3 @@
+-- The answer the author of a question accepted, if any
+ALTER TABLE questions ADD COLUMN accepted_answer_id INTEGER
+    REFERENCES answers (id) ON DELETE SET NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE answers DROP COLUMN comment_count;
+ALTER TABLE questions DROP COLUMN comment_count;
+
+DROP TABLE IF EXISTS comments;
@@ -0,0 +1,18 @@
+-- Short remarks on a question or an answer, the row they are on counts the visible ones
+CREATE TABLE IF NOT EXISTS comments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    content TEXT NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    question_id INTEGER REFERENCES questions (id) ON DELETE CASCADE,
+    answer_id INTEGER REFERENCES answers (id) ON DELETE CASCADE,
+    account_id INTEGER REFERENCES accounts (id),
+    status TEXT NOT NULL DEFAULT 'published',
+    CHECK ((question_id IS NULL) <> (answer_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS comments_question_id ON comments (question_id);
+CREATE INDEX IF NOT EXISTS comments_answer_id ON comments (answer_id);
+CREATE INDEX IF NOT EXISTS comments_status ON comments (status);
+
+ALTER TABLE questions ADD COLUMN comment_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE answers ADD COLUMN comment_count INTEGER NOT NULL DEFAULT 0;
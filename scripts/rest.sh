@@ -1,6 +1,7 @@
-# create question
+# create question, the token is the one returned by the login route
 curl --location --request POST 'localhost:3030/questions' \
      --header 'Content-Type: application/json' \
+     --header 'Authorization: Bearer v2.local.xxxx' \
      --data-raw '{
         "title": "Do you know Perl 6?",
         "content": "Grammar is awesome",
@@ -13,6 +14,7 @@ curl --location --request GET 'localhost:3030/questions'
 # update question
 curl --location --request PUT 'localhost:3030/questions/dev' \
     --header 'Content-Type: application/json' \
+    --header 'Authorization: Bearer v2.local.xxxx' \
     --data-raw '{
         "id": "dev",
         "title": "Do you know Raku?",
@@ -20,11 +22,13 @@ curl --location --request PUT 'localhost:3030/questions/dev' \
     }'
 
 # delete question
-curl --location --request DELETE 'localhost:3030/questions/dev'
+curl --location --request DELETE 'localhost:3030/questions/dev' \
+    --header 'Authorization: Bearer v2.local.xxxx'
 
 # post answer
 curl --location --request POST 'localhost:3030/answers' \
      --header 'Content-Type: application/x-www-form-urlencoded' \
+     --header 'Authorization: Bearer v2.local.xxxx' \
      --data-urlencode 'id=raku' \
      --data-urlencode 'questionId=dev' \
      --data-urlencode 'content=This is the question I had.'
@@ -51,4 +55,32 @@ curl --location --request POST 'localhost:3030/login' \
     --data-raw '{
     "email": "test@email.com",
     "password": "cleartext"
-}'
\ No newline at end of file
+}'
+
+# list accounts, needs a token of an admin account
+curl --location --request GET 'localhost:3030/admin/accounts' \
+    --header 'Authorization: Bearer v2.local.xxxx'
+
+# promote an account to moderator (user, moderator or admin)
+curl --location --request PUT 'localhost:3030/admin/accounts/1/role' \
+    --header 'Content-Type: application/json' \
+    --header 'Authorization: Bearer v2.local.xxxx' \
+    --data-raw '{
+    "role": "moderator"
+}'
+
+# list questions waiting for review, needs a token of a moderator or admin account
+curl --location --request GET 'localhost:3030/moderation/questions' \
+    --header 'Authorization: Bearer v2.local.xxxx'
+
+# publish a queued question, or hide it with /reject
+curl --location --request PUT 'localhost:3030/moderation/questions/1/approve' \
+    --header 'Authorization: Bearer v2.local.xxxx'
+
+# the same for answers
+curl --location --request PUT 'localhost:3030/moderation/answers/1/reject' \
+    --header 'Authorization: Bearer v2.local.xxxx'
+
+# hit and miss counters of the profanity check cache
+curl --location --request GET 'localhost:3030/moderation/cache' \
+    --header 'Authorization: Bearer v2.local.xxxx'
\ No newline at end of file
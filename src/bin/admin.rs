@@ -0,0 +1,655 @@
+#![warn(clippy::all)]
+//! Manages accounts and content directly in the store, without going through the web server
+
+use clap::{Parser, Subcommand, ValueEnum};
+use futures_util::StreamExt;
+use handle_errors::Error;
+use rand::distributions::{Alphanumeric, DistString};
+use rust_web::config::{Config, FailurePolicy, ProfanityBackend};
+use rust_web::profanity::{self, Moderated, Moderation};
+use rust_web::routes::authentication::hash_password;
+use rust_web::secrets::{SecretsProvider, APILAYER_API_KEY};
+use rust_web::store::{self, Store};
+use rust_web::transfer::{self, ExportFormat, ImportFormat, ImportReport, RowError};
+use rust_web::types::account::{Account, AccountId, AccountProfile, Role, Session};
+use rust_web::types::answer::Answer;
+use rust_web::types::moderation::ModerationStatus;
+use rust_web::types::question::Question;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+
+/// Length of the passwords generated for new accounts and resets
+const GENERATED_PASSWORD_LENGTH: usize = 20;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Manages accounts and content of the Q&A web service")]
+struct Args {
+    /// Path to the TOML configuration file of the server
+    #[arg(long, env = "CONFIG_FILE")]
+    config: Option<PathBuf>,
+    /// Directory with one file per secret, e.g. `/run/secrets`
+    #[arg(long, env = "SECRETS_DIR")]
+    secrets_dir: Option<PathBuf>,
+    /// Connection string of the database
+    #[arg(long, env = "DATABASE_URL")]
+    database_url: Option<String>,
+    /// How results are printed
+    #[arg(long, value_enum, default_value_t = Output::Table)]
+    output: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Output {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create, disable and promote accounts, reset passwords
+    Accounts {
+        #[command(subcommand)]
+        command: AccountCommand,
+    },
+    /// List and delete questions
+    Questions {
+        #[command(subcommand)]
+        command: ContentCommand,
+    },
+    /// List and delete answers
+    Answers {
+        #[command(subcommand)]
+        command: ContentCommand,
+    },
+    /// Check existing questions and answers again with the configured moderation
+    Moderate {
+        /// Only content with this status is checked, can be given more than once
+        #[arg(long = "status", default_value = "unchecked")]
+        statuses: Vec<ModerationStatus>,
+        /// Report what would change without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write every question with its answers as JSON Lines or CSV
+    Export {
+        #[arg(long, default_value = "jsonl")]
+        format: ExportFormat,
+        /// Written to stdout unless a file is given
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Add the questions and answers of a file, which belong to the given account afterwards
+    Import {
+        file: PathBuf,
+        /// `jsonl`, `csv` or `legacy` (the map of `questions/questions.json`),
+        /// guessed from the file extension unless given
+        #[arg(long)]
+        format: Option<ImportFormat>,
+        /// E-mail address of the account which owns the imported content
+        #[arg(long)]
+        account: String,
+        /// Check the imported text with the configured moderation
+        #[arg(long)]
+        moderate: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum AccountCommand {
+    List,
+    /// Create an account, a password gets generated unless one is given
+    Create {
+        email: String,
+        #[arg(long, default_value = "user")]
+        role: Role,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Keep an account from logging in
+    Disable {
+        email: String,
+    },
+    Enable {
+        email: String,
+    },
+    /// Set a new password, a password gets generated unless one is given
+    ResetPassword {
+        email: String,
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Change the role of an account
+    Role {
+        email: String,
+        role: Role,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ContentCommand {
+    List {
+        /// Only list content with this status, everything is listed otherwise
+        #[arg(long)]
+        status: Option<ModerationStatus>,
+    },
+    Delete {
+        id: i32,
+    },
+}
+
+/// An account along with the password which was generated for it
+#[derive(Serialize, Debug)]
+struct Credentials {
+    #[serde(flatten)]
+    account: AccountProfile,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct Deleted {
+    id: i32,
+    deleted: bool,
+}
+
+/// What happened to a question or answer when it was checked again
+#[derive(Serialize, Debug)]
+struct Recheck {
+    kind: &'static str,
+    id: i32,
+    old_status: ModerationStatus,
+    new_status: ModerationStatus,
+    changed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Something which can be printed as a row of a table
+trait Row {
+    const HEADERS: &'static [&'static str];
+
+    fn cells(&self) -> Vec<String>;
+}
+
+impl Row for Credentials {
+    const HEADERS: &'static [&'static str] = &["ID", "EMAIL", "ROLE", "DISABLED", "PASSWORD"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.account.id.0.to_string(),
+            self.account.email.clone(),
+            self.account.role.as_str().to_string(),
+            self.account.disabled.to_string(),
+            self.password.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+impl Row for AccountProfile {
+    const HEADERS: &'static [&'static str] = &["ID", "EMAIL", "ROLE", "DISABLED"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.0.to_string(),
+            self.email.clone(),
+            self.role.as_str().to_string(),
+            self.disabled.to_string(),
+        ]
+    }
+}
+
+impl Row for Question {
+    const HEADERS: &'static [&'static str] = &["ID", "STATUS", "ACCOUNT", "TAGS", "TITLE"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.0.to_string(),
+            self.status.as_str().to_string(),
+            account(&self.account_id),
+            self.tags.clone().unwrap_or_default().join(","),
+            self.title.clone(),
+        ]
+    }
+}
+
+impl Row for Answer {
+    const HEADERS: &'static [&'static str] = &["ID", "STATUS", "ACCOUNT", "QUESTION", "CONTENT"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.id.0.to_string(),
+            self.status.as_str().to_string(),
+            account(&self.account_id),
+            self.question_id.0.to_string(),
+            self.content.clone(),
+        ]
+    }
+}
+
+impl Row for Deleted {
+    const HEADERS: &'static [&'static str] = &["ID", "DELETED"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![self.id.to_string(), self.deleted.to_string()]
+    }
+}
+
+impl Row for RowError {
+    const HEADERS: &'static [&'static str] = &["ROW", "KIND", "ID", "ERROR"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.row.to_string(),
+            self.kind.to_string(),
+            self.id.clone().unwrap_or_else(|| "-".to_string()),
+            self.error.clone(),
+        ]
+    }
+}
+
+impl Row for Recheck {
+    const HEADERS: &'static [&'static str] = &["KIND", "ID", "OLD", "NEW", "CHANGED", "ERROR"];
+
+    fn cells(&self) -> Vec<String> {
+        vec![
+            self.kind.to_string(),
+            self.id.to_string(),
+            self.old_status.as_str().to_string(),
+            self.new_status.as_str().to_string(),
+            self.changed.to_string(),
+            self.error.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+fn account(account_id: &Option<AccountId>) -> String {
+    account_id
+        .as_ref()
+        .map(|id| id.0.to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn print<T: Row + Serialize>(output: Output, rows: &[T]) {
+    match output {
+        Output::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(rows).expect("rows serialize to JSON")
+            )
+        }
+        Output::Table => {
+            let cells: Vec<Vec<String>> = rows.iter().map(Row::cells).collect();
+            let mut widths: Vec<usize> = T::HEADERS.iter().map(|h| h.len()).collect();
+            for row in &cells {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+
+            let line = |row: Vec<String>| {
+                let padded: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                    .collect();
+                println!("{}", padded.join("  ").trim_end());
+            };
+            line(T::HEADERS.iter().map(|h| h.to_string()).collect());
+            for row in cells {
+                line(row);
+            }
+        }
+    }
+}
+
+/// The CLI acts as an admin, which passes every ownership check of the store
+fn admin_session() -> Session {
+    Session {
+        exp: chrono::Utc::now() + chrono::Duration::hours(1),
+        account_id: AccountId(0),
+        nbf: chrono::Utc::now(),
+        role: Role::Admin,
+    }
+}
+
+fn generate_password() -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), GENERATED_PASSWORD_LENGTH)
+}
+
+async fn accounts(store: &Store, output: Output, command: AccountCommand) -> Result<(), Error> {
+    let (account, password) = match command {
+        AccountCommand::List => {
+            print(output, &store.get_accounts().await?);
+            return Ok(());
+        }
+        AccountCommand::Create {
+            email,
+            role,
+            password,
+        } => {
+            let generated = password.is_none();
+            let password = password.unwrap_or_else(generate_password);
+            store
+                .add_account(Account {
+                    id: None,
+                    email: email.clone(),
+                    password: hash_password(password.as_bytes()),
+                    role,
+                    disabled: false,
+                })
+                .await?;
+            let account = store.get_account(email).await?;
+            let account = AccountProfile {
+                id: account.id.expect("stored accounts have an id"),
+                email: account.email,
+                role: account.role,
+                disabled: account.disabled,
+            };
+            (account, generated.then_some(password))
+        }
+        AccountCommand::Disable { email } => {
+            let id = account_id(store, email).await?;
+            (store.set_account_disabled(id, true).await?, None)
+        }
+        AccountCommand::Enable { email } => {
+            let id = account_id(store, email).await?;
+            (store.set_account_disabled(id, false).await?, None)
+        }
+        AccountCommand::ResetPassword { email, password } => {
+            let id = account_id(store, email).await?;
+            let generated = password.is_none();
+            let password = password.unwrap_or_else(generate_password);
+            let account = store
+                .update_account_password(id, hash_password(password.as_bytes()))
+                .await?;
+            (account, generated.then_some(password))
+        }
+        AccountCommand::Role { email, role } => {
+            let id = account_id(store, email).await?;
+            (store.update_account_role(id, role).await?, None)
+        }
+    };
+
+    print(output, &[Credentials { account, password }]);
+    Ok(())
+}
+
+async fn account_id(store: &Store, email: String) -> Result<AccountId, Error> {
+    Ok(store
+        .get_account(email)
+        .await?
+        .id
+        .expect("stored accounts have an id"))
+}
+
+async fn all_questions(
+    store: &Store,
+    statuses: &[ModerationStatus],
+) -> Result<Vec<Question>, Error> {
+    let mut questions = Vec::new();
+    for status in statuses {
+        questions.extend(store.get_questions_by_status(*status).await?);
+    }
+    questions.sort_by_key(|q| q.id.0);
+    Ok(questions)
+}
+
+async fn all_answers(store: &Store, statuses: &[ModerationStatus]) -> Result<Vec<Answer>, Error> {
+    let mut answers = Vec::new();
+    for status in statuses {
+        answers.extend(store.get_answers_by_status(*status).await?);
+    }
+    answers.sort_by_key(|a| a.id.0);
+    Ok(answers)
+}
+
+fn statuses(status: Option<ModerationStatus>) -> Vec<ModerationStatus> {
+    match status {
+        Some(status) => vec![status],
+        None => ModerationStatus::ALL.to_vec(),
+    }
+}
+
+async fn questions(store: &Store, output: Output, command: ContentCommand) -> Result<(), Error> {
+    match command {
+        ContentCommand::List { status } => {
+            print(output, &all_questions(store, &statuses(status)).await?)
+        }
+        ContentCommand::Delete { id } => {
+            let deleted = store.delete_question(id, &admin_session()).await?;
+            print(output, &[Deleted { id, deleted }]);
+        }
+    }
+    Ok(())
+}
+
+async fn answers(store: &Store, output: Output, command: ContentCommand) -> Result<(), Error> {
+    match command {
+        ContentCommand::List { status } => {
+            print(output, &all_answers(store, &statuses(status)).await?)
+        }
+        ContentCommand::Delete { id } => {
+            let deleted = store.delete_answer(id, &admin_session()).await?;
+            print(output, &[Deleted { id, deleted }]);
+        }
+    }
+    Ok(())
+}
+
+/// Moderates the fields of a post, content with words the policy rejects gets the status
+/// `rejected` and stays as it is, every other error leaves the post unchanged
+async fn moderate_fields(
+    moderation: &Moderation,
+    fields: Vec<String>,
+) -> Result<(Vec<String>, ModerationStatus), Error> {
+    let mut moderated = Vec::with_capacity(fields.len());
+    let mut status = ModerationStatus::Published;
+    for field in &fields {
+        match moderation.moderate(field.clone()).await {
+            Ok(Moderated {
+                content,
+                status: field_status,
+            }) => {
+                moderated.push(content);
+                status = status.and(field_status);
+            }
+            Err(Error::BadWordsFound(_)) => return Ok((fields, ModerationStatus::Rejected)),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok((moderated, status))
+}
+
+async fn moderate(
+    store: &Store,
+    moderation: &Moderation,
+    statuses: &[ModerationStatus],
+    dry_run: bool,
+) -> Result<Vec<Recheck>, Error> {
+    let session = admin_session();
+    let mut rechecks = Vec::new();
+
+    for question in all_questions(store, statuses).await? {
+        let mut recheck = Recheck {
+            kind: "question",
+            id: question.id.0,
+            old_status: question.status,
+            new_status: question.status,
+            changed: false,
+            error: None,
+        };
+        let fields = vec![question.title.clone(), question.content.clone()];
+        match moderate_fields(moderation, fields).await {
+            Ok((fields, status)) => {
+                let mut updated = question.clone();
+                updated.content = fields[1].clone();
+                updated.title = fields[0].clone();
+                updated.status = status;
+                recheck.new_status = status;
+                recheck.changed = updated.title != question.title
+                    || updated.content != question.content
+                    || updated.status != question.status;
+                if recheck.changed && !dry_run {
+                    if let Err(e) = store
+                        .update_question(updated, question.id.0, &session)
+                        .await
+                    {
+                        recheck.error = Some(e.to_string());
+                    }
+                }
+            }
+            Err(e) => recheck.error = Some(e.to_string()),
+        }
+        rechecks.push(recheck);
+    }
+
+    for answer in all_answers(store, statuses).await? {
+        let mut recheck = Recheck {
+            kind: "answer",
+            id: answer.id.0,
+            old_status: answer.status,
+            new_status: answer.status,
+            changed: false,
+            error: None,
+        };
+        match moderate_fields(moderation, vec![answer.content.clone()]).await {
+            Ok((fields, status)) => {
+                let mut updated = answer.clone();
+                updated.content = fields[0].clone();
+                updated.status = status;
+                recheck.new_status = status;
+                recheck.changed =
+                    updated.content != answer.content || updated.status != answer.status;
+                if recheck.changed && !dry_run {
+                    if let Err(e) = store.update_answer(updated, answer.id.0, &session).await {
+                        recheck.error = Some(e.to_string());
+                    }
+                }
+            }
+            Err(e) => recheck.error = Some(e.to_string()),
+        }
+        rechecks.push(recheck);
+    }
+
+    Ok(rechecks)
+}
+
+async fn export(
+    store: Store,
+    format: ExportFormat,
+    file: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match file {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(std::io::stdout().lock()),
+    });
+    let mut chunks = pin!(transfer::export(store, format));
+    while let Some(chunk) = chunks.next().await {
+        writer.write_all(chunk?.as_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// The format of a file named `*.csv` is CSV, `*.json` is the legacy map of questions
+fn import_format(path: &Path) -> ImportFormat {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => ImportFormat::Csv,
+        Some("json") => ImportFormat::Legacy,
+        _ => ImportFormat::Jsonl,
+    }
+}
+
+fn print_report(output: Output, report: &ImportReport) {
+    match output {
+        Output::Json => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(report).expect("reports serialize to JSON")
+            )
+        }
+        Output::Table => {
+            if !report.errors.is_empty() {
+                print(output, &report.errors);
+            }
+            println!(
+                "Imported {} questions and {} answers, {} failed",
+                report.questions,
+                report.answers,
+                report.errors.len()
+            );
+        }
+    }
+}
+
+/// Builds the moderation of the server, except that a failing checker leaves the content
+/// as it is, instead of applying the failure policy to content which was fine so far
+fn moderation(config: &mut Config) -> Result<Moderation, Box<dyn std::error::Error>> {
+    let secrets = SecretsProvider::new(config.secrets.dir.clone());
+    config.profanity.api_key = match config.profanity.backend {
+        ProfanityBackend::ApiLayer => secrets.require(APILAYER_API_KEY)?,
+        _ => secrets.get(APILAYER_API_KEY)?.unwrap_or_default(),
+    };
+    let checker = profanity::from_config(&config.profanity)?;
+    Ok(Moderation::new(
+        checker,
+        config.moderation.policy,
+        FailurePolicy::FailClosed,
+    ))
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = Config::read(args.config.as_ref())?;
+    if let Some(url) = args.database_url {
+        config.database.url = url;
+    }
+    if let Some(dir) = args.secrets_dir {
+        config.secrets.dir = Some(dir);
+    }
+    config.validate()?;
+
+    let store = store::connect(&config.database).await?;
+    match args.command {
+        Command::Accounts { command } => accounts(&store, args.output, command).await?,
+        Command::Questions { command } => questions(&store, args.output, command).await?,
+        Command::Answers { command } => answers(&store, args.output, command).await?,
+        Command::Moderate { statuses, dry_run } => {
+            let moderation = moderation(&mut config)?;
+            let rechecks = moderate(&store, &moderation, &statuses, dry_run).await?;
+            print(args.output, &rechecks);
+        }
+        Command::Export { format, file } => export(store, format, file).await?,
+        Command::Import {
+            file,
+            format,
+            account,
+            moderate,
+        } => {
+            let moderation = if moderate {
+                Some(moderation(&mut config)?)
+            } else {
+                None
+            };
+            let account_id = account_id(&store, account).await?;
+            let format = format.unwrap_or_else(|| import_format(&file));
+            let input = std::fs::read_to_string(&file)?;
+            let report =
+                transfer::import(&store, moderation.as_ref(), account_id, format, &input).await?;
+            print_report(args.output, &report);
+        }
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(e) = run(Args::parse()).await {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,567 @@
+use crate::secrets::{PasetoKeys, Secret};
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::fmt::Formatter;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+
+/// Default location of the configuration file, it is optional
+/// as long as it is not explicitly requested with `--config`
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Command line flags, every flag can also be set through
+/// the environment variable next to it.
+/// Flags take precedence over the environment, which takes
+/// precedence over the configuration file.
+#[derive(Parser, Debug)]
+#[command(version, about = "Q&A web service")]
+pub struct Args {
+    /// Path to the TOML configuration file
+    #[arg(long, env = "CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+    /// Directory with one file per secret, e.g. `/run/secrets`
+    #[arg(long, env = "SECRETS_DIR")]
+    pub secrets_dir: Option<PathBuf>,
+    /// Address the web server binds to
+    #[arg(long, env = "HOST")]
+    pub host: Option<IpAddr>,
+    /// Port the web server listens on
+    #[arg(long, env = "PORT")]
+    pub port: Option<u16>,
+    /// Connection string of the database
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+    /// Maximum number of connections in the database pool
+    #[arg(long, env = "DATABASE_MAX_CONNECTIONS")]
+    pub database_max_connections: Option<u32>,
+    /// Questions the memory store starts with
+    #[arg(long, env = "DATABASE_SEED_FILE")]
+    pub database_seed_file: Option<PathBuf>,
+    /// Which errors we want to log (info, warn or error) per target
+    #[arg(long, env = "RUST_LOG")]
+    pub log_level: Option<String>,
+    /// Offset to UTC in hours used for the log timestamps
+    #[arg(long, env = "LOG_UTC_OFFSET", allow_hyphen_values = true)]
+    pub log_utc_offset: Option<i8>,
+    /// How long a token handed out on login stays valid
+    #[arg(long, env = "TOKEN_LIFETIME_HOURS")]
+    pub token_lifetime_hours: Option<i64>,
+    /// Which checker moderates the content
+    #[arg(long, env = "PROFANITY_BACKEND")]
+    pub profanity_backend: Option<ProfanityBackend>,
+    /// Directory with the word lists of the `word_list` backend
+    #[arg(long, env = "PROFANITY_WORD_LIST_DIR")]
+    pub profanity_word_list_dir: Option<PathBuf>,
+    /// Languages whose word lists get loaded, separated by commas
+    #[arg(long, env = "PROFANITY_LANGUAGES", value_delimiter = ',')]
+    pub profanity_languages: Option<Vec<String>>,
+    /// Endpoint of the bad words API, including the query string
+    #[arg(long, env = "PROFANITY_API_URL")]
+    pub profanity_api_url: Option<String>,
+    /// How often a request to the bad words API gets retried on transient errors
+    #[arg(long, env = "PROFANITY_MAX_RETRIES")]
+    pub profanity_max_retries: Option<u32>,
+    /// How long a single request to the bad words API may take
+    #[arg(long, env = "PROFANITY_TIMEOUT_SECONDS")]
+    pub profanity_timeout_seconds: Option<u64>,
+    /// Failed checks in a row after which the checker is not called for a while
+    #[arg(long, env = "PROFANITY_BREAKER_FAILURE_THRESHOLD")]
+    pub profanity_breaker_failure_threshold: Option<u32>,
+    /// How long the checker is not called once the failure threshold is reached
+    #[arg(long, env = "PROFANITY_BREAKER_OPEN_SECONDS")]
+    pub profanity_breaker_open_seconds: Option<u64>,
+    /// How many check results are remembered, 0 turns the cache off
+    #[arg(long, env = "PROFANITY_CACHE_SIZE")]
+    pub profanity_cache_size: Option<usize>,
+    /// How long a check result is remembered
+    #[arg(long, env = "PROFANITY_CACHE_TTL_SECONDS")]
+    pub profanity_cache_ttl_seconds: Option<u64>,
+    /// What happens to posts with bad words in them
+    #[arg(long, env = "MODERATION_POLICY")]
+    pub moderation_policy: Option<ModerationPolicy>,
+    /// What happens to posts when the checker fails
+    #[arg(long, env = "MODERATION_FAILURE_POLICY")]
+    pub moderation_failure_policy: Option<FailurePolicy>,
+    /// Page size of lists when the request has no `limit`
+    #[arg(long, env = "PAGINATION_DEFAULT_PAGE_SIZE")]
+    pub pagination_default_page_size: Option<i32>,
+    /// Largest page a list returns
+    #[arg(long, env = "PAGINATION_MAX_PAGE_SIZE")]
+    pub pagination_max_page_size: Option<i32>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Runs instead of the web server if given
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Manage the database schema of the configured store
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrateAction {
+    /// Apply every pending migration
+    Up,
+    /// Revert the most recently applied migrations
+    Down {
+        /// How many migrations to revert
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+    /// List the migrations and whether they are applied
+    Status,
+    /// Make the migrations table match the migrations of this build: forget migrations
+    /// which failed or no longer exist, and accept changed checksums
+    Repair,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub log: LogConfig,
+    pub auth: AuthConfig,
+    pub profanity: ProfanityConfig,
+    pub moderation: ModerationConfig,
+    pub pagination: PaginationConfig,
+    pub secrets: SecretsConfig,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: IpAddr,
+    pub port: u16,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// `postgres://` or `sqlite:` for a database, `memory://` for a store which lives in memory only
+    pub url: String,
+    pub max_connections: u32,
+    /// Questions in the format of `questions/questions.json` the `memory://` store starts with
+    pub seed_file: Option<PathBuf>,
+}
+
+impl DatabaseConfig {
+    /// The url with the user and password left out, so it can be printed
+    ///
+    /// ```
+    /// # use rust_web::config::DatabaseConfig;
+    /// let config = DatabaseConfig {
+    ///     url: "postgres://app:secret@db:5432/rustwebdev".to_string(),
+    ///     ..DatabaseConfig::default()
+    /// };
+    /// assert_eq!(config.redacted_url(), "postgres://***@db:5432/rustwebdev");
+    /// ```
+    pub fn redacted_url(&self) -> String {
+        let Some((scheme, rest)) = self.url.split_once("://") else {
+            return self.url.clone();
+        };
+        let host_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        match rest[..host_end].rfind('@') {
+            Some(at) => format!("{}://***{}", scheme, &rest[at..]),
+            None => self.url.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Filter in the `tracing_subscriber::EnvFilter` syntax
+    pub filter: String,
+    /// Offset to UTC in hours used for the log timestamps
+    pub utc_offset: i8,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// How long a token handed out on login stays valid
+    pub token_lifetime_hours: i64,
+    /// Filled from the secrets provider, never read from the configuration file
+    #[serde(skip)]
+    pub keys: PasetoKeys,
+}
+
+/// Which `ProfanityChecker` moderates the content
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ProfanityBackend {
+    /// The bad words API of apilayer.com
+    #[default]
+    #[serde(rename = "apilayer")]
+    #[value(name = "apilayer")]
+    ApiLayer,
+    /// A local list of bad words, needs no network access
+    WordList,
+    /// Accepts all content unchanged
+    None,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfanityConfig {
+    pub backend: ProfanityBackend,
+    /// Directory with a `<language>.txt` word list and an optional
+    /// `<language>.allow.txt` allow-list per language, used by the `word_list` backend
+    pub word_list_dir: PathBuf,
+    /// Languages whose word lists get loaded
+    pub languages: Vec<String>,
+    /// Endpoint of the bad words API, including the query string
+    pub api_url: String,
+    /// Filled from the secrets provider, never read from the configuration file
+    #[serde(skip)]
+    pub api_key: Secret,
+    /// How often a request gets retried on transient errors
+    pub max_retries: u32,
+    /// How long a single request to the API may take
+    pub timeout_seconds: u64,
+    /// Failed checks in a row after which the API is not called for a while
+    pub breaker_failure_threshold: u32,
+    /// How long the API is not called once the failure threshold is reached
+    pub breaker_open_seconds: u64,
+    /// How many check results are remembered, 0 turns the cache off
+    pub cache_size: usize,
+    /// How long a check result is remembered
+    pub cache_ttl_seconds: u64,
+}
+
+/// What happens to questions and answers with bad words in them
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum ModerationPolicy {
+    /// Replace the bad words with `*` and publish the post
+    #[default]
+    Censor,
+    /// Refuse the post and tell the author which words are not allowed
+    Reject,
+    /// Store the post as it is, but hide it until a moderator approves it
+    Queue,
+}
+
+/// What happens to questions and answers when the checker fails,
+/// e.g. because the moderation API is down
+#[derive(Deserialize, ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Refuse the post with a 503
+    #[default]
+    FailClosed,
+    /// Publish the post unchecked and flag it to be checked again later
+    FailOpen,
+    /// Store the post, but hide it until a moderator approves it
+    Queue,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModerationConfig {
+    pub policy: ModerationPolicy,
+    pub failure_policy: FailurePolicy,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaginationConfig {
+    /// Page size of lists when the request has no `limit`
+    pub default_page_size: i32,
+    /// Largest page a list returns, bigger limits are cut down to it
+    pub max_page_size: i32,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecretsConfig {
+    /// Directory with one file per secret, e.g. `/run/secrets`
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 3030,
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: "postgres://localhost:5432/rustwebdev".to_string(),
+            max_connections: 5,
+            seed_file: None,
+        }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            filter: "handle_errors=warn,practical_rust_book=info,warp=error".to_string(),
+            utc_offset: 0,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        AuthConfig {
+            token_lifetime_hours: 24,
+            keys: PasetoKeys::default(),
+        }
+    }
+}
+
+impl Default for ProfanityConfig {
+    fn default() -> Self {
+        ProfanityConfig {
+            backend: ProfanityBackend::default(),
+            word_list_dir: PathBuf::from("profanity"),
+            languages: vec!["en".to_string()],
+            api_url: "https://api.apilayer.com/bad_words?censor_character=*".to_string(),
+            api_key: Secret::default(),
+            max_retries: 3,
+            timeout_seconds: 10,
+            breaker_failure_threshold: 5,
+            breaker_open_seconds: 30,
+            cache_size: 10_000,
+            cache_ttl_seconds: 3600,
+        }
+    }
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        PaginationConfig {
+            default_page_size: 20,
+            max_page_size: 100,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    CannotReadFile(PathBuf, std::io::Error),
+    CannotParseFile(PathBuf, toml::de::Error),
+    Invalid(Vec<String>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::CannotReadFile(path, err) => {
+                write!(f, "Cannot read config file {}: {}", path.display(), err)
+            }
+            ConfigError::CannotParseFile(path, err) => {
+                write!(f, "Cannot parse config file {}: {}", path.display(), err)
+            }
+            ConfigError::Invalid(problems) => {
+                write!(f, "Invalid configuration:")?;
+                for problem in problems {
+                    write!(f, "\n  - {}", problem)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Reads the command line flags and the environment and builds
+    /// the configuration for the server from them, along with the command to run instead
+    pub fn load() -> Result<(Config, Option<Command>), ConfigError> {
+        let mut args = Args::parse();
+        let command = args.command.take();
+        Ok((Config::from_args(args)?, command))
+    }
+
+    pub fn from_args(args: Args) -> Result<Config, ConfigError> {
+        let mut config = Config::read(args.config.as_ref())?;
+
+        if let Some(host) = args.host {
+            config.server.host = host;
+        }
+        if let Some(port) = args.port {
+            config.server.port = port;
+        }
+        if let Some(url) = args.database_url {
+            config.database.url = url;
+        }
+        if let Some(max_connections) = args.database_max_connections {
+            config.database.max_connections = max_connections;
+        }
+        if let Some(filter) = args.log_level {
+            config.log.filter = filter;
+        }
+        if let Some(utc_offset) = args.log_utc_offset {
+            config.log.utc_offset = utc_offset;
+        }
+        if let Some(dir) = args.secrets_dir {
+            config.secrets.dir = Some(dir);
+        }
+        if let Some(seed_file) = args.database_seed_file {
+            config.database.seed_file = Some(seed_file);
+        }
+        if let Some(hours) = args.token_lifetime_hours {
+            config.auth.token_lifetime_hours = hours;
+        }
+        if let Some(backend) = args.profanity_backend {
+            config.profanity.backend = backend;
+        }
+        if let Some(dir) = args.profanity_word_list_dir {
+            config.profanity.word_list_dir = dir;
+        }
+        if let Some(languages) = args.profanity_languages {
+            config.profanity.languages = languages;
+        }
+        if let Some(url) = args.profanity_api_url {
+            config.profanity.api_url = url;
+        }
+        if let Some(max_retries) = args.profanity_max_retries {
+            config.profanity.max_retries = max_retries;
+        }
+        if let Some(seconds) = args.profanity_timeout_seconds {
+            config.profanity.timeout_seconds = seconds;
+        }
+        if let Some(threshold) = args.profanity_breaker_failure_threshold {
+            config.profanity.breaker_failure_threshold = threshold;
+        }
+        if let Some(seconds) = args.profanity_breaker_open_seconds {
+            config.profanity.breaker_open_seconds = seconds;
+        }
+        if let Some(size) = args.profanity_cache_size {
+            config.profanity.cache_size = size;
+        }
+        if let Some(seconds) = args.profanity_cache_ttl_seconds {
+            config.profanity.cache_ttl_seconds = seconds;
+        }
+        if let Some(policy) = args.moderation_policy {
+            config.moderation.policy = policy;
+        }
+        if let Some(policy) = args.moderation_failure_policy {
+            config.moderation.failure_policy = policy;
+        }
+        if let Some(size) = args.pagination_default_page_size {
+            config.pagination.default_page_size = size;
+        }
+        if let Some(size) = args.pagination_max_page_size {
+            config.pagination.max_page_size = size;
+        }
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads the given configuration file, or `config.toml` if there is one,
+    /// without validating it or applying the environment
+    pub fn read(path: Option<&PathBuf>) -> Result<Config, ConfigError> {
+        match path {
+            Some(path) => Config::from_file(path),
+            None => {
+                let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if path.exists() {
+                    Config::from_file(&path)
+                } else {
+                    Ok(Config::default())
+                }
+            }
+        }
+    }
+
+    fn from_file(path: &PathBuf) -> Result<Config, ConfigError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::CannotReadFile(path.clone(), e))?;
+        toml::from_str(&content).map_err(|e| ConfigError::CannotParseFile(path.clone(), e))
+    }
+
+    /// Collects every problem instead of stopping at the first one,
+    /// so a broken deployment can be fixed in one go
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.port == 0 {
+            problems.push("server.port must not be 0".to_string());
+        }
+        let memory = self.database.url.starts_with("memory://");
+        if !memory
+            && !self.database.url.starts_with("postgres://")
+            && !self.database.url.starts_with("postgresql://")
+            && !self.database.url.starts_with("sqlite:")
+        {
+            problems.push(format!(
+                "database.url must be a postgres://, sqlite: or memory:// URL, got '{}'",
+                self.database.url
+            ));
+        }
+        if let Some(seed_file) = &self.database.seed_file {
+            if !memory {
+                problems.push("database.seed_file only works with a memory:// URL".to_string());
+            } else if !seed_file.is_file() {
+                problems.push(format!(
+                    "database.seed_file '{}' does not exist",
+                    seed_file.display()
+                ));
+            }
+        }
+        if self.database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if !(-25..=25).contains(&self.log.utc_offset) {
+            problems.push(format!(
+                "log.utc_offset must be between -25 and 25 hours, got {}",
+                self.log.utc_offset
+            ));
+        }
+        if self.pagination.default_page_size < 1 {
+            problems.push("pagination.default_page_size must be at least 1".to_string());
+        }
+        if self.pagination.max_page_size < self.pagination.default_page_size {
+            problems.push(format!(
+                "pagination.max_page_size must be at least the default_page_size of {}, got {}",
+                self.pagination.default_page_size, self.pagination.max_page_size
+            ));
+        }
+        if self.auth.token_lifetime_hours <= 0 {
+            problems.push("auth.token_lifetime_hours must be positive".to_string());
+        }
+        if self.profanity.backend == ProfanityBackend::WordList
+            && self.profanity.languages.is_empty()
+        {
+            problems.push("profanity.languages must list at least one language".to_string());
+        }
+        if self.profanity.timeout_seconds == 0 {
+            problems.push("profanity.timeout_seconds must be at least 1".to_string());
+        }
+        if self.profanity.breaker_failure_threshold == 0 {
+            problems.push("profanity.breaker_failure_threshold must be at least 1".to_string());
+        }
+        if self.profanity.backend == ProfanityBackend::ApiLayer && self.profanity.api_url.is_empty()
+        {
+            problems.push("profanity.api_url must not be empty".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.server.host, self.server.port)
+    }
+}
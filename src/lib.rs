@@ -0,0 +1,8 @@
+#![warn(clippy::all)]
+pub mod config;
+pub mod profanity;
+pub mod routes;
+pub mod secrets;
+pub mod store;
+pub mod transfer;
+pub mod types;
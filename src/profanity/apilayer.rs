@@ -0,0 +1,85 @@
+use crate::config::ProfanityConfig;
+use crate::profanity::{BadWordsResponse, ProfanityChecker};
+use crate::secrets::Secret;
+use async_trait::async_trait;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct APIResponse {
+    message: String,
+}
+
+async fn transform_error(res: reqwest::Response) -> handle_errors::APILayerError {
+    let status = res.status().as_u16();
+    // Proxies and gateways answer with HTML or plain text, keep that as the message
+    let body = res.text().await.unwrap_or_default();
+    let message = match serde_json::from_str::<APIResponse>(&body) {
+        Ok(res) => res.message,
+        Err(_) => body,
+    };
+    handle_errors::APILayerError { status, message }
+}
+
+/// Censors content with the bad words API of apilayer.com
+#[derive(Debug, Clone)]
+pub struct ApiLayerChecker {
+    api_url: String,
+    api_key: Secret,
+    /// Shared by all requests, so connections to the API get reused
+    client: ClientWithMiddleware,
+}
+
+impl ApiLayerChecker {
+    pub fn new(config: &ProfanityConfig) -> Self {
+        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(config.max_retries);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .expect("Cannot build the HTTP client");
+        let client = ClientBuilder::new(client)
+            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
+            .build();
+
+        ApiLayerChecker {
+            api_url: config.api_url.clone(),
+            api_key: config.api_key.clone(),
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityChecker for ApiLayerChecker {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        let res = self
+            .client
+            .post(&self.api_url)
+            .header("apikey", self.api_key.expose())
+            .body(content)
+            .send()
+            .await
+            .map_err(handle_errors::Error::MiddlewareReqwestAPIError)?;
+
+        // Checks whether the respinse status was successful
+        if !res.status().is_success() {
+            // The status also indicates whether it was a client or server error.
+            if res.status().is_client_error() {
+                let err = transform_error(res).await;
+                // Returns a client error with our APILayerError encapsulated
+                return Err(handle_errors::Error::ClientError(err))?;
+            } else {
+                let err = transform_error(res).await;
+                // Returns a server error with our APILayerError encapsulated
+                return Err(handle_errors::Error::ServerError(err))?;
+            }
+        }
+
+        match res.json::<BadWordsResponse>().await {
+            Ok(res) => Ok(res),
+            Err(e) => Err(handle_errors::Error::ReqwestAPIError(e)),
+        }
+    }
+}
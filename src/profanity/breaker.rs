@@ -0,0 +1,104 @@
+use crate::profanity::{BadWordsResponse, Checker, ProfanityChecker};
+use async_trait::async_trait;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{event, Level};
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Checks go through, counting the failures in a row
+    Closed { failures: u32 },
+    /// Checks fail right away until the time is up
+    Open { until: Instant },
+    /// One trial check is running, its result decides whether to close again
+    HalfOpen { since: Instant },
+}
+
+/// Stops calling a failing checker for a while, so an outage of the
+/// moderation API does not hold up every write for the length of its retries
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    inner: Checker,
+    failure_threshold: u32,
+    open_duration: Duration,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(inner: Checker, failure_threshold: u32, open_duration: Duration) -> Self {
+        CircuitBreaker {
+            inner,
+            failure_threshold,
+            open_duration,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Decides whether a check may reach the inner checker
+    fn acquire(&self) -> Result<(), handle_errors::Error> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            State::Closed { .. } => Ok(()),
+            State::Open { until } if now < until => {
+                Err(handle_errors::Error::ModerationUnavailable)
+            }
+            // A trial which never came back (e.g. the request got dropped) must not block forever
+            State::HalfOpen { since } if now.duration_since(since) < self.open_duration => {
+                Err(handle_errors::Error::ModerationUnavailable)
+            }
+            State::Open { .. } | State::HalfOpen { .. } => {
+                event!(
+                    Level::INFO,
+                    "moderation circuit half-open, sending a trial check"
+                );
+                *state = State::HalfOpen { since: now };
+                Ok(())
+            }
+        }
+    }
+
+    fn record(&self, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        *state = match (*state, success) {
+            (_, true) => State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 < self.failure_threshold => {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (State::Open { until }, false) => State::Open { until },
+            (_, false) => {
+                event!(
+                    Level::WARN,
+                    seconds = self.open_duration.as_secs(),
+                    "moderation circuit open"
+                );
+                State::Open {
+                    until: Instant::now() + self.open_duration,
+                }
+            }
+        };
+    }
+}
+
+/// Whether the error says the checker is down. A request it refused (4xx) got an answer,
+/// so it does not count towards opening the circuit.
+fn is_outage(error: &handle_errors::Error) -> bool {
+    matches!(
+        error,
+        handle_errors::Error::ServerError(_)
+            | handle_errors::Error::ReqwestAPIError(_)
+            | handle_errors::Error::MiddlewareReqwestAPIError(_)
+    )
+}
+
+#[async_trait]
+impl ProfanityChecker for CircuitBreaker {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        self.acquire()?;
+        let res = self.inner.check(content).await;
+        self.record(!matches!(res, Err(ref error) if is_outage(error)));
+        res
+    }
+}
@@ -0,0 +1,154 @@
+use crate::profanity::{BadWordsResponse, Checker, ProfanityChecker};
+use async_trait::async_trait;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+use tracing::{event, Level};
+
+type Key = [u8; 32];
+
+/// Counters of the cache, served on `/moderation/cache`
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct CacheStats {
+    /// Checks answered from the cache
+    pub hits: u64,
+    /// Checks which had to ask the inner checker
+    pub misses: u64,
+    /// Checks which waited for an identical check already in flight
+    pub coalesced: u64,
+    /// Entries currently in the cache
+    pub entries: usize,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    results: HashMap<Key, (Instant, BadWordsResponse)>,
+    /// Keys in the order they were inserted, the oldest gets evicted first
+    order: VecDeque<(Key, Instant)>,
+}
+
+/// Remembers the results of another checker, keyed by the SHA-256 hash of the content.
+/// Identical checks running at the same time only reach the inner checker once.
+#[derive(Debug)]
+pub struct CachedChecker {
+    inner: Checker,
+    max_entries: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    in_flight: Mutex<HashMap<Key, Arc<OnceCell<BadWordsResponse>>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+impl CachedChecker {
+    pub fn new(inner: Checker, max_entries: usize, ttl: Duration) -> Self {
+        CachedChecker {
+            inner,
+            max_entries,
+            ttl,
+            entries: Mutex::new(Entries::default()),
+            in_flight: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().results.len(),
+        }
+    }
+
+    fn get(&self, key: &Key) -> Option<BadWordsResponse> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.results.get(key) {
+            Some((inserted, res)) if inserted.elapsed() < self.ttl => Some(res.clone()),
+            Some(_) => {
+                entries.results.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: Key, res: BadWordsResponse) {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.results.insert(key, (now, res));
+        entries.order.push_back((key, now));
+
+        while entries.results.len() > self.max_entries {
+            match entries.order.pop_front() {
+                Some((key, inserted)) => {
+                    // The key might have been inserted again since, then this is a stale position
+                    if entries.results.get(&key).map(|(i, _)| *i) == Some(inserted) {
+                        entries.results.remove(&key);
+                    }
+                }
+                None => break,
+            }
+        }
+        // Positions of keys which expired or got replaced pile up otherwise
+        if entries.order.len() > self.max_entries.saturating_mul(2) {
+            let Entries { results, order } = &mut *entries;
+            order.retain(|(key, inserted)| results.get(key).map(|(i, _)| i) == Some(inserted));
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityChecker for CachedChecker {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        let key: Key = Sha256::digest(content.as_bytes()).into();
+
+        if let Some(res) = self.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            event!(Level::DEBUG, "profanity cache hit");
+            return Ok(res);
+        }
+
+        let cell = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_default()
+            .clone();
+
+        let mut asked = false;
+        let res = cell
+            .get_or_try_init(|| {
+                asked = true;
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                event!(Level::DEBUG, "profanity cache miss");
+                self.inner.check(content)
+            })
+            .await
+            .cloned();
+
+        if asked {
+            // Fills the cache first, so no check between the two steps asks again
+            if let Ok(res) = &res {
+                self.insert(key, res.clone());
+            }
+            // Only the check which asked the inner checker cleans up, and only its own cell
+            let mut in_flight = self.in_flight.lock().unwrap();
+            if in_flight.get(&key).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+                in_flight.remove(&key);
+            }
+        } else {
+            self.coalesced.fetch_add(1, Ordering::Relaxed);
+        }
+
+        res
+    }
+}
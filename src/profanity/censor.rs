@@ -0,0 +1,287 @@
+use crate::profanity::{BadWord, BadWordsResponse, ProfanityChecker};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Replaces every character of a bad word
+const CENSOR_CHARACTER: char = '*';
+
+/// How an entry of the word list has to line up with a word of the content.
+/// A `*` at the start or the end of an entry matches any other characters there.
+#[derive(Debug, Clone)]
+enum Wildcard {
+    /// `word*`
+    Prefix(String),
+    /// `*word`
+    Suffix(String),
+    /// `*word*`
+    Contains(String),
+}
+
+impl Wildcard {
+    fn matches(&self, word: &str) -> bool {
+        match self {
+            Wildcard::Prefix(entry) => word.starts_with(entry.as_str()),
+            Wildcard::Suffix(entry) => word.ends_with(entry.as_str()),
+            Wildcard::Contains(entry) => word.contains(entry.as_str()),
+        }
+    }
+}
+
+/// Censors content locally against per-language word lists, without any network access.
+/// Words are compared after folding case, diacritics and leetspeak,
+/// so `Sh1t`, `shït` and `SHIIIT` all match `shit`.
+#[derive(Debug, Clone, Default)]
+pub struct Censor {
+    /// Normalized entry -> entry as written in the word list
+    exact: HashMap<String, String>,
+    /// Entries with repeated characters collapsed -> normalized entry and entry as written,
+    /// several entries can collapse to the same characters
+    collapsed: HashMap<String, Vec<(String, String)>>,
+    wildcards: Vec<(Wildcard, String)>,
+    /// Normalized words which are never censored, even if an entry matches them,
+    /// e.g. `scunthorpe` for `*cunt*`
+    allowed: HashSet<String>,
+}
+
+impl Censor {
+    pub fn new<W, A>(words: W, allowed: A) -> Self
+    where
+        W: IntoIterator,
+        W::Item: AsRef<str>,
+        A: IntoIterator,
+        A::Item: AsRef<str>,
+    {
+        let mut censor = Censor::default();
+        for word in words {
+            censor.add_word(word.as_ref());
+        }
+        censor.allowed = allowed
+            .into_iter()
+            .map(|word| normalize(word.as_ref().trim()).0)
+            .filter(|word| !word.is_empty())
+            .collect();
+        censor
+    }
+
+    /// Loads `<dir>/<language>.txt` for every language, and the allow-list
+    /// `<dir>/<language>.allow.txt` next to it if there is one
+    pub fn from_dir(dir: &Path, languages: &[String]) -> Result<Self, std::io::Error> {
+        let mut words = Vec::new();
+        let mut allowed = Vec::new();
+
+        for language in languages {
+            let path = dir.join(format!("{}.txt", language));
+            words.extend(read_list(&path)?);
+
+            let path = dir.join(format!("{}.allow.txt", language));
+            if path.exists() {
+                allowed.extend(read_list(&path)?);
+            }
+        }
+
+        Ok(Censor::new(words, allowed))
+    }
+
+    fn add_word(&mut self, entry: &str) {
+        let entry = entry.trim();
+        let (starts, ends) = (entry.starts_with('*'), entry.ends_with('*'));
+        let word = normalize(entry.trim_matches('*')).0;
+        if word.is_empty() {
+            return;
+        }
+
+        let original = entry.trim_matches('*').to_string();
+        match (starts, ends) {
+            (false, false) => {
+                self.collapsed
+                    .entry(collapse(&word))
+                    .or_default()
+                    .push((word.clone(), original.clone()));
+                self.exact.insert(word, original);
+            }
+            (false, true) => self.wildcards.push((Wildcard::Prefix(word), original)),
+            (true, false) => self.wildcards.push((Wildcard::Suffix(word), original)),
+            (true, true) => self.wildcards.push((Wildcard::Contains(word), original)),
+        }
+    }
+
+    /// Returns the word list entry the normalized word matches, if any
+    fn find(&self, word: &str) -> Option<&String> {
+        if self.allowed.contains(word) {
+            return None;
+        }
+        if let Some(entry) = self.exact.get(word) {
+            return Some(entry);
+        }
+        if let Some(entry) = self.find_wildcard(word) {
+            return Some(entry);
+        }
+        // Only words with repeated characters are compared collapsed,
+        // and only to entries they repeat characters of, otherwise `as` and `aas` would match `ass`
+        let collapsed = collapse(word);
+        if collapsed == word {
+            return None;
+        }
+        self.collapsed
+            .get(&collapsed)
+            .and_then(|entries| entries.iter().find(|(entry, _)| stretches(word, entry)))
+            .map(|(_, original)| original)
+            .or_else(|| self.find_wildcard(&collapsed))
+    }
+
+    fn find_wildcard(&self, word: &str) -> Option<&String> {
+        self.wildcards
+            .iter()
+            .find(|(wildcard, _)| wildcard.matches(word))
+            .map(|(_, entry)| entry)
+    }
+
+    /// Replaces every character of a bad word with `*` and reports
+    /// each bad word with its position in the content
+    pub fn censor(&self, content: &str) -> BadWordsResponse {
+        let chars: Vec<char> = content.chars().collect();
+        let mut censored = String::with_capacity(content.len());
+        let mut bad_words = Vec::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            if !is_word_char(chars[i]) {
+                censored.push(chars[i]);
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < chars.len() && is_word_char(chars[i]) {
+                i += 1;
+            }
+            let mut end = i;
+            // Leetspeak only counts inside of words, `shit!` is `shit` at the end of a sentence
+            while end > start + 1 && chars[end - 1] == '!' {
+                end -= 1;
+            }
+
+            let original: String = chars[start..end].iter().collect();
+            let (word, deviations) = normalize(&original);
+            match self.find(&word) {
+                Some(entry) => {
+                    censored.extend(std::iter::repeat_n(CENSOR_CHARACTER, end - start));
+                    bad_words.push(BadWord {
+                        original,
+                        word: entry.clone(),
+                        deviations,
+                        info: 0,
+                        start: start as i64,
+                        end: end as i64,
+                        replaced_len: (end - start) as i64,
+                    });
+                }
+                None => censored.push_str(&original),
+            }
+            censored.extend(&chars[end..i]);
+        }
+
+        BadWordsResponse {
+            content: content.to_string(),
+            bad_words_total: bad_words.len() as i64,
+            bad_words_list: bad_words,
+            censored_content: censored,
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityChecker for Censor {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        Ok(self.censor(&content))
+    }
+}
+
+/// Reads one entry per line, empty lines and lines starting with `#` are skipped
+fn read_list(path: &Path) -> Result<Vec<String>, std::io::Error> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| std::io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect())
+}
+
+/// Characters which stand in for letters in leetspeak
+fn leet(c: char) -> Option<char> {
+    match c {
+        '0' => Some('o'),
+        '1' | '!' => Some('i'),
+        '3' => Some('e'),
+        '4' | '@' => Some('a'),
+        '5' | '$' => Some('s'),
+        '7' | '+' => Some('t'),
+        '8' => Some('b'),
+        '9' => Some('g'),
+        '|' => Some('l'),
+        _ => None,
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || leet(c).is_some()
+}
+
+/// Folds a word into the form the word lists are compared in: lowercase,
+/// without diacritics and with leetspeak replaced by the letters it stands for.
+/// Also returns how many characters had to be replaced, apart from the case.
+fn normalize(word: &str) -> (String, i64) {
+    let mut normalized = String::with_capacity(word.len());
+    let mut deviations = 0;
+
+    for c in word.chars() {
+        let lowercase: String = c.to_lowercase().collect();
+        let folded: String = match leet(c) {
+            Some(letter) => letter.to_string(),
+            None => c
+                .nfd()
+                .filter(|c| !is_combining_mark(*c))
+                .flat_map(char::to_lowercase)
+                .collect(),
+        };
+        if folded != lowercase {
+            deviations += 1;
+        }
+        normalized.push_str(&folded);
+    }
+
+    (normalized, deviations)
+}
+
+/// Collapses runs of the same character, `shiiit` becomes `shit`
+fn collapse(word: &str) -> String {
+    runs(word).into_iter().map(|(c, _)| c).collect()
+}
+
+/// The runs of the same character in a word, `ass` is `a` once and `s` twice
+fn runs(word: &str) -> Vec<(char, usize)> {
+    let mut runs: Vec<(char, usize)> = Vec::new();
+    for c in word.chars() {
+        match runs.last_mut() {
+            Some((last, count)) if *last == c => *count += 1,
+            _ => runs.push((c, 1)),
+        }
+    }
+    runs
+}
+
+/// Whether the word is the entry with some of its characters repeated more often,
+/// `asss` stretches `ass`, but `aas` does not
+fn stretches(word: &str, entry: &str) -> bool {
+    let (word, entry) = (runs(word), runs(entry));
+    word.len() == entry.len()
+        && word
+            .iter()
+            .zip(&entry)
+            .all(|((c, count), (entry_c, entry_count))| c == entry_c && count >= entry_count)
+}
@@ -0,0 +1,166 @@
+use crate::config::{FailurePolicy, ModerationPolicy, ProfanityBackend, ProfanityConfig};
+use crate::types::moderation::ModerationStatus;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::sync::Arc;
+use tracing::{event, Level};
+
+pub mod apilayer;
+pub mod breaker;
+pub mod cache;
+pub mod censor;
+
+#[cfg(test)]
+mod tests;
+
+use apilayer::ApiLayerChecker;
+use censor::Censor;
+
+/// A bad word found in the content, in the format of the apilayer bad words API
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BadWord {
+    /// The word as it was written in the content
+    pub original: String,
+    /// The entry of the word list it matched
+    pub word: String,
+    /// How many characters had to be normalized for the match
+    pub deviations: i64,
+    pub info: i64,
+    /// Character offset of the first character of the word
+    #[serde(default)]
+    pub start: i64,
+    /// Character offset behind the last character of the word
+    #[serde(default)]
+    pub end: i64,
+    #[serde(rename = "replacedLen")]
+    pub replaced_len: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BadWordsResponse {
+    pub content: String,
+    pub bad_words_total: i64,
+    pub bad_words_list: Vec<BadWord>,
+    pub censored_content: String,
+}
+
+impl BadWordsResponse {
+    /// A response for content without any bad words
+    pub fn clean(content: String) -> Self {
+        BadWordsResponse {
+            censored_content: content.clone(),
+            content,
+            bad_words_total: 0,
+            bad_words_list: Vec::new(),
+        }
+    }
+}
+
+/// Moderates user generated content before it gets stored
+#[async_trait]
+pub trait ProfanityChecker: Debug + Send + Sync {
+    /// Finds the bad words in the content and censors them with `*`
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error>;
+}
+
+/// The checker shared by all routes
+pub type Checker = Arc<dyn ProfanityChecker>;
+
+/// Accepts all content as it is
+#[derive(Debug, Clone, Default)]
+pub struct NoopChecker;
+
+#[async_trait]
+impl ProfanityChecker for NoopChecker {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, handle_errors::Error> {
+        Ok(BadWordsResponse::clean(content))
+    }
+}
+
+/// Content which passed moderation and can be stored
+#[derive(Debug, Clone)]
+pub struct Moderated {
+    pub content: String,
+    pub status: ModerationStatus,
+}
+
+/// Applies the `moderation.policy` of the deployment to the result of the checker
+#[derive(Debug, Clone)]
+pub struct Moderation {
+    checker: Checker,
+    policy: ModerationPolicy,
+    failure_policy: FailurePolicy,
+}
+
+impl Moderation {
+    pub fn new(checker: Checker, policy: ModerationPolicy, failure_policy: FailurePolicy) -> Self {
+        Moderation {
+            checker,
+            policy,
+            failure_policy,
+        }
+    }
+
+    pub async fn moderate(&self, content: String) -> Result<Moderated, handle_errors::Error> {
+        // The checker takes the content, keep it around in case it fails
+        let res = match self.checker.check(content.clone()).await {
+            Ok(res) => res,
+            Err(e) => return self.on_failure(content, e),
+        };
+        if res.bad_words_list.is_empty() {
+            return Ok(Moderated {
+                content: res.content,
+                status: ModerationStatus::Published,
+            });
+        }
+
+        match self.policy {
+            ModerationPolicy::Censor => Ok(Moderated {
+                content: res.censored_content,
+                status: ModerationStatus::Published,
+            }),
+            ModerationPolicy::Reject => Err(handle_errors::Error::BadWordsFound(
+                res.bad_words_list
+                    .into_iter()
+                    .map(|bad_word| bad_word.original)
+                    .collect(),
+            )),
+            // A moderator gets to see the content as it was written
+            ModerationPolicy::Queue => Ok(Moderated {
+                content: res.content,
+                status: ModerationStatus::Pending,
+            }),
+        }
+    }
+
+    fn on_failure(
+        &self,
+        content: String,
+        error: handle_errors::Error,
+    ) -> Result<Moderated, handle_errors::Error> {
+        event!(Level::ERROR, "Cannot check content: {}", error);
+        match self.failure_policy {
+            FailurePolicy::FailClosed => Err(handle_errors::Error::ModerationUnavailable),
+            FailurePolicy::FailOpen => Ok(Moderated {
+                content,
+                status: ModerationStatus::Unchecked,
+            }),
+            FailurePolicy::Queue => Ok(Moderated {
+                content,
+                status: ModerationStatus::Pending,
+            }),
+        }
+    }
+}
+
+/// Builds the checker selected by `profanity.backend`
+pub fn from_config(config: &ProfanityConfig) -> Result<Checker, std::io::Error> {
+    Ok(match config.backend {
+        ProfanityBackend::ApiLayer => Arc::new(ApiLayerChecker::new(config)),
+        ProfanityBackend::WordList => {
+            Arc::new(Censor::from_dir(&config.word_list_dir, &config.languages)?)
+        }
+        ProfanityBackend::None => Arc::new(NoopChecker),
+    })
+}
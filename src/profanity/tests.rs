@@ -0,0 +1,391 @@
+//! Behavior of the checkers and of what wraps them. Nothing here calls the real
+//! moderation API, a local warp server stands in for it where one is needed.
+
+use crate::config::{FailurePolicy, ModerationPolicy, ProfanityConfig};
+use crate::profanity::apilayer::ApiLayerChecker;
+use crate::profanity::breaker::CircuitBreaker;
+use crate::profanity::cache::CachedChecker;
+use crate::profanity::censor::Censor;
+use crate::profanity::{BadWordsResponse, Moderation, ProfanityChecker};
+use crate::types::moderation::ModerationStatus;
+use async_trait::async_trait;
+use handle_errors::Error;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use warp::http::StatusCode;
+use warp::Filter;
+
+/// Counts its calls and fails while `failing` is set, or refuses the request while
+/// `refusing` is set, each call takes `delay`
+#[derive(Debug, Default)]
+struct Flaky {
+    failing: AtomicBool,
+    refusing: AtomicBool,
+    calls: AtomicUsize,
+    delay: Duration,
+}
+
+impl Flaky {
+    fn failing() -> Arc<Self> {
+        let flaky = Flaky::default();
+        flaky.failing.store(true, Ordering::SeqCst);
+        Arc::new(flaky)
+    }
+
+    fn slow(delay: Duration) -> Arc<Self> {
+        Arc::new(Flaky {
+            delay,
+            ..Flaky::default()
+        })
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl ProfanityChecker for Flaky {
+    async fn check(&self, content: String) -> Result<BadWordsResponse, Error> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        if self.refusing.load(Ordering::SeqCst) {
+            return Err(Error::ClientError(handle_errors::APILayerError {
+                status: 400,
+                message: "Bad Request".to_string(),
+            }));
+        }
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(Error::ServerError(handle_errors::APILayerError {
+                status: 503,
+                message: "Service Unavailable".to_string(),
+            }));
+        }
+        Ok(BadWordsResponse::clean(content))
+    }
+}
+
+#[tokio::test]
+async fn breaker_opens_and_closes_again() {
+    let flaky = Flaky::failing();
+    let breaker = CircuitBreaker::new(flaky.clone(), 2, Duration::from_millis(50));
+
+    // Closed: failures reach the checker until the threshold
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    assert_eq!(flaky.calls(), 2);
+
+    // Open: checks fail right away
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ModerationUnavailable)
+    ));
+    assert_eq!(flaky.calls(), 2);
+
+    // Half-open: a failed trial opens the circuit again
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    assert_eq!(flaky.calls(), 3);
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ModerationUnavailable)
+    ));
+    assert_eq!(flaky.calls(), 3);
+
+    // Half-open: a successful trial closes it
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    flaky.failing.store(false, Ordering::SeqCst);
+    assert!(breaker.check("a".to_string()).await.is_ok());
+    assert!(breaker.check("a".to_string()).await.is_ok());
+    assert_eq!(flaky.calls(), 5);
+
+    // Closed again, with the count of failures starting over
+    flaky.failing.store(true, Ordering::SeqCst);
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    assert_eq!(flaky.calls(), 7);
+}
+
+#[tokio::test]
+async fn refused_checks_do_not_open_the_breaker() {
+    let flaky = Arc::new(Flaky::default());
+    flaky.refusing.store(true, Ordering::SeqCst);
+    let breaker = CircuitBreaker::new(flaky.clone(), 2, Duration::from_secs(60));
+
+    for _ in 0..3 {
+        assert!(matches!(
+            breaker.check("a".to_string()).await,
+            Err(Error::ClientError(_))
+        ));
+    }
+    assert_eq!(flaky.calls(), 3);
+
+    // A refused check gets an answer, so it ends a run of failures as well
+    flaky.refusing.store(false, Ordering::SeqCst);
+    flaky.failing.store(true, Ordering::SeqCst);
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    flaky.refusing.store(true, Ordering::SeqCst);
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ClientError(_))
+    ));
+    flaky.refusing.store(false, Ordering::SeqCst);
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ServerError(_))
+    ));
+    assert_eq!(flaky.calls(), 7);
+    assert!(matches!(
+        breaker.check("a".to_string()).await,
+        Err(Error::ModerationUnavailable)
+    ));
+}
+
+#[tokio::test]
+async fn failure_policies_decide_about_unchecked_content() {
+    let moderate = |failure_policy| async move {
+        Moderation::new(Flaky::failing(), ModerationPolicy::Censor, failure_policy)
+            .moderate("Hello".to_string())
+            .await
+    };
+
+    let res = moderate(FailurePolicy::FailClosed).await;
+    assert!(matches!(res, Err(Error::ModerationUnavailable)));
+
+    let moderated = moderate(FailurePolicy::FailOpen).await.unwrap();
+    assert_eq!(moderated.content, "Hello");
+    assert_eq!(moderated.status, ModerationStatus::Unchecked);
+
+    let moderated = moderate(FailurePolicy::Queue).await.unwrap();
+    assert_eq!(moderated.content, "Hello");
+    assert_eq!(moderated.status, ModerationStatus::Pending);
+
+    // Nothing changes while the checker works
+    let working = Arc::new(Flaky::default());
+    let moderated = Moderation::new(working, ModerationPolicy::Censor, FailurePolicy::Queue)
+        .moderate("Hello".to_string())
+        .await
+        .unwrap();
+    assert_eq!(moderated.status, ModerationStatus::Published);
+}
+
+/// Starts a server answering every request with the status and body
+fn api_layer(status: StatusCode, body: &'static str) -> ApiLayerChecker {
+    let route = warp::any().map(move || warp::reply::with_status(body, status));
+    let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+    tokio::spawn(server);
+    ApiLayerChecker::new(&ProfanityConfig {
+        api_url: format!("http://{}/bad_words", addr),
+        max_retries: 0,
+        ..ProfanityConfig::default()
+    })
+}
+
+#[tokio::test]
+async fn api_layer_errors_keep_their_message() {
+    let body = r#"{"message": "Invalid authentication credentials"}"#;
+    let checker = api_layer(StatusCode::UNAUTHORIZED, body);
+    match checker.check("Hello".to_string()).await {
+        Err(Error::ClientError(e)) => {
+            assert_eq!(e.status, 401);
+            assert_eq!(e.message, "Invalid authentication credentials");
+        }
+        res => panic!("Expected a client error, got {:?}", res),
+    }
+
+    // Gateways in front of the API do not answer with JSON
+    let checker = api_layer(StatusCode::BAD_GATEWAY, "<html>502 Bad Gateway</html>");
+    match checker.check("Hello".to_string()).await {
+        Err(Error::ServerError(e)) => {
+            assert_eq!(e.status, 502);
+            assert_eq!(e.message, "<html>502 Bad Gateway</html>");
+        }
+        res => panic!("Expected a server error, got {:?}", res),
+    }
+}
+
+fn censored(censor: &Censor, content: &str) -> (String, Vec<String>) {
+    let res = censor.censor(content);
+    let words = res
+        .bad_words_list
+        .into_iter()
+        .map(|bad_word| bad_word.word)
+        .collect();
+    (res.censored_content, words)
+}
+
+#[test]
+fn censor_sees_through_leetspeak_and_repetition() {
+    let censor = Censor::new(["ass", "shit", "*fuck*"], Vec::<String>::new());
+
+    assert_eq!(
+        censored(&censor, "Sh1t"),
+        ("****".to_string(), vec!["shit".to_string()])
+    );
+    assert_eq!(
+        censored(&censor, "$h!t"),
+        ("****".to_string(), vec!["shit".to_string()])
+    );
+    assert_eq!(
+        censored(&censor, "shït"),
+        ("****".to_string(), vec!["shit".to_string()])
+    );
+    assert_eq!(
+        censored(&censor, "SHIIIT"),
+        ("******".to_string(), vec!["shit".to_string()])
+    );
+    assert_eq!(
+        censored(&censor, "@$$"),
+        ("***".to_string(), vec!["ass".to_string()])
+    );
+    assert_eq!(
+        censored(&censor, "asss"),
+        ("****".to_string(), vec!["ass".to_string()])
+    );
+    assert_eq!(
+        censored(&censor, "f4cking fvck"),
+        ("f4cking fvck".to_string(), vec![])
+    );
+    let (content, words) = censored(&censor, "MOTHERFUCKER");
+    assert_eq!(content, "************");
+    assert_eq!(words, vec!["fuck".to_string()]);
+
+    // Repeating other characters of a word does not make it a bad word
+    for clean in ["as", "aas", "a$$et", "@sset", "passes", "shiit5"] {
+        assert_eq!(
+            censored(&censor, clean),
+            (clean.to_string(), vec![]),
+            "{}",
+            clean
+        );
+    }
+}
+
+#[test]
+fn censor_keeps_words_apart() {
+    let censor = Censor::new(["ass", "shit"], Vec::<String>::new());
+
+    let res = censor.censor("Oh shit! Kiss my ass, you ass-hat.");
+    assert_eq!(res.censored_content, "Oh ****! Kiss my ***, you ***-hat.");
+    let positions: Vec<_> = res
+        .bad_words_list
+        .iter()
+        .map(|w| (w.start, w.end))
+        .collect();
+    assert_eq!(positions, vec![(3, 7), (17, 20), (26, 29)]);
+
+    // Words which only contain an entry are fine without a wildcard
+    for clean in ["class", "assume", "bass", "shitake"] {
+        assert_eq!(
+            censored(&censor, clean),
+            (clean.to_string(), vec![]),
+            "{}",
+            clean
+        );
+    }
+}
+
+#[test]
+fn censor_allows_the_allow_list() {
+    let censor = Censor::from_dir(Path::new("profanity"), &["en".to_string()]).unwrap();
+
+    assert_eq!(
+        censored(&censor, "Scunthorpe"),
+        ("Scunthorpe".to_string(), vec![])
+    );
+    assert_eq!(
+        censored(&censor, "SCUNTHORPE"),
+        ("SCUNTHORPE".to_string(), vec![])
+    );
+    let (content, words) = censored(&censor, "cunts");
+    assert_eq!(content, "*****");
+    assert_eq!(words, vec!["cunt".to_string()]);
+}
+
+#[tokio::test]
+async fn cache_asks_once_for_identical_checks() {
+    let flaky = Flaky::slow(Duration::from_millis(50));
+    let cache = Arc::new(CachedChecker::new(
+        flaky.clone(),
+        10,
+        Duration::from_secs(60),
+    ));
+
+    let mut checks = tokio::task::JoinSet::new();
+    for _ in 0..5 {
+        let cache = cache.clone();
+        checks.spawn(async move { cache.check("Hello".to_string()).await });
+    }
+    while let Some(res) = checks.join_next().await {
+        assert_eq!(res.unwrap().unwrap().content, "Hello");
+    }
+    assert_eq!(flaky.calls(), 1);
+    let stats = cache.stats();
+    assert_eq!(
+        (stats.hits, stats.misses, stats.coalesced, stats.entries),
+        (0, 1, 4, 1)
+    );
+
+    assert!(cache.check("Hello".to_string()).await.is_ok());
+    assert!(cache.check("Hello!".to_string()).await.is_ok());
+    assert_eq!(flaky.calls(), 2);
+    let stats = cache.stats();
+    assert_eq!(
+        (stats.hits, stats.misses, stats.coalesced, stats.entries),
+        (1, 2, 4, 2)
+    );
+}
+
+#[tokio::test]
+async fn cache_forgets_expired_and_evicted_results() {
+    let flaky = Flaky::slow(Duration::ZERO);
+    let cache = CachedChecker::new(flaky.clone(), 2, Duration::from_millis(50));
+
+    assert!(cache.check("a".to_string()).await.is_ok());
+    assert!(cache.check("a".to_string()).await.is_ok());
+    assert_eq!(flaky.calls(), 1);
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert!(cache.check("a".to_string()).await.is_ok());
+    assert_eq!(flaky.calls(), 2);
+
+    // The oldest result makes room for new ones
+    assert!(cache.check("b".to_string()).await.is_ok());
+    assert!(cache.check("c".to_string()).await.is_ok());
+    assert_eq!(cache.stats().entries, 2);
+    assert!(cache.check("c".to_string()).await.is_ok());
+    assert_eq!(flaky.calls(), 4);
+    assert!(cache.check("a".to_string()).await.is_ok());
+    assert_eq!(flaky.calls(), 5);
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (2, 5, 2));
+
+    // Failures are not remembered
+    flaky.failing.store(true, Ordering::SeqCst);
+    assert!(cache.check("d".to_string()).await.is_err());
+    assert!(cache.check("d".to_string()).await.is_err());
+    assert_eq!(flaky.calls(), 7);
+}
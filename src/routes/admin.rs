@@ -0,0 +1,32 @@
+use crate::store::Store;
+use crate::types::account::{AccountId, RoleUpdate, Session};
+use tracing::{event, Level};
+
+pub async fn get_accounts(
+    _session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_accounts().await {
+        Ok(accounts) => Ok(warp::reply::json(&accounts)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn update_role(
+    id: i32,
+    session: Session,
+    store: Store,
+    update: RoleUpdate,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        admin_id = session.account_id.0,
+        account_id = id,
+        role = update.role.as_str(),
+        "changing account role"
+    );
+    match store.update_account_role(AccountId(id), update.role).await {
+        Ok(account) => Ok(warp::reply::json(&account)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
@@ -1,23 +1,212 @@
+use crate::profanity::Moderation;
+use crate::routes::question::visible_question;
+use crate::routes::with_links;
 use crate::store::Store;
-use crate::types::answer::NewAnswer;
+use crate::types::account::Session;
+use crate::types::answer::{extract_answer_sort, Answer, AnswerSort, AnswerUpdate, NewAnswer};
+use crate::types::pagination::Pagination;
+use crate::types::vote::NewVote;
+use handle_errors::Error;
+use tracing::{event, Level};
+use warp::http::header::LOCATION;
 use warp::http::StatusCode;
-use crate::profanity::check_profanity;
 
 pub async fn add_answer(
+    session: Session,
     store: Store,
+    moderation: Moderation,
     answer: NewAnswer,
 ) -> Result<impl warp::reply::Reply, warp::Rejection> {
-    let content = match
-        check_profanity(answer.content).await {
+    // Hidden questions take no answers, and their answers are not worth a moderation call
+    visible_question(&store, answer.question_id.0).await?;
+    let content = match moderation.moderate(answer.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
     let answer = NewAnswer {
-        content,
+        content: content.content,
         question_id: answer.question_id,
     };
-    match store.add_answer(answer).await {
-        Ok(_) => Ok(warp::reply::with_status("Answer added", StatusCode::OK)),
+    match store
+        .add_answer(answer, session.account_id, content.status)
+        .await
+    {
+        Ok(answer) => Ok(warp::reply::with_status(
+            warp::reply::with_header(
+                warp::reply::json(&answer),
+                LOCATION,
+                format!("/answers/{}", answer.id.0),
+            ),
+            StatusCode::CREATED,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// A visible answer to a visible question
+pub async fn get_answer(id: i32, store: Store) -> Result<impl warp::Reply, warp::Rejection> {
+    let answer = visible_answer(&store, id).await?;
+    Ok(warp::reply::json(&answer))
+}
+
+/// Hidden answers and answers to hidden questions are not found
+pub async fn visible_answer(store: &Store, id: i32) -> Result<Answer, warp::Rejection> {
+    let answer = match store.get_answer(id).await {
+        Ok(answer) if answer.status.is_visible() => answer,
+        Ok(_) => return Err(warp::reject::custom(Error::AnswerNotFound)),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    visible_question(store, answer.question_id.0).await?;
+    Ok(answer)
+}
+
+/// Sets the vote of the account on a visible answer
+pub async fn vote_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+    vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        answer_id = id,
+        "voting on answer"
+    );
+    visible_answer(&store, id).await?;
+    match store
+        .vote_answer(id, session.account_id, Some(vote.value))
+        .await
+    {
+        Ok(answer) => Ok(warp::reply::json(&answer)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Takes back the vote of the account on a visible answer
+pub async fn retract_answer_vote(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        answer_id = id,
+        "retracting answer vote"
+    );
+    visible_answer(&store, id).await?;
+    match store.vote_answer(id, session.account_id, None).await {
+        Ok(answer) => Ok(warp::reply::json(&answer)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn update_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+    moderation: Moderation,
+    update: AnswerUpdate,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        answer_id = id,
+        "updating answer"
+    );
+    let answer = match store.get_answer(id).await {
+        Ok(answer) => answer,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    // Only the author's edits are worth a moderation call
+    store.ensure_answer_owner(id, &session).await?;
+    let content = match moderation.moderate(update.content).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let answer = Answer {
+        content: content.content,
+        status: content.status,
+        ..answer
+    };
+    match store.update_answer(answer, id, &session).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn delete_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        answer_id = id,
+        "deleting answer"
+    );
+    match store.delete_answer(id, &session).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            format!("Answer {} deleted", id),
+            StatusCode::OK,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// The visible answers to a visible question, `limit` and `offset` page through them,
+/// `after` asks for cursor pagination and `sort` orders them
+pub async fn get_answers(
+    question_id: i32,
+    params: Vec<(String, String)>,
+    pagination: Pagination,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let sort = extract_answer_sort(&params)?;
+    visible_question(&store, question_id).await?;
+    let path = format!("/questions/{}/answers", question_id);
+
+    if pagination.keyset {
+        if let Some(after) = &pagination.after {
+            if after.key.is_some() != (sort == AnswerSort::MostVoted) {
+                return Err(warp::reject::custom(Error::InvalidParameter(
+                    "after is a cursor of another sort".to_string(),
+                )));
+            }
+        }
+        let mut page = store
+            .get_answer_page(
+                question_id,
+                sort,
+                pagination.after.as_ref(),
+                pagination.limit,
+            )
+            .await?;
+        if pagination.total {
+            page.total = Some(store.count_answers(question_id).await?);
+        }
+        let links = pagination.links(&path, &params, page.has_more, page.next_cursor.as_ref());
+        return Ok(with_links(warp::reply::json(&page), links));
+    }
+
+    // One more than the page shows whether there is a next page
+    match store
+        .get_answers(
+            question_id,
+            sort,
+            Some(pagination.limit.saturating_add(1)),
+            pagination.offset,
+        )
+        .await
+    {
+        Ok(mut answers) => {
+            let has_more = pagination.truncate(&mut answers);
+            let links = pagination.links(&path, &params, has_more, None);
+            Ok(with_links(warp::reply::json(&answers), links))
+        }
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
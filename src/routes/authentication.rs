@@ -1,10 +1,12 @@
-use warp::http::StatusCode;
+use crate::config::AuthConfig;
 use crate::store::Store;
-use crate::types::account::{Account, AccountId};
+use crate::types::account::{Account, AccountId, NewAccount, Role, Session};
 use argon2::{self, Config};
-use paseto::v2::local_paseto;
-use rand::random;
 use chrono::prelude::*;
+use rand::random;
+use std::future;
+use warp::http::StatusCode;
+use warp::Filter;
 
 // The hash function returns a string, the hashed version of the clear-text password
 pub fn hash_password(password: &[u8]) -> String {
@@ -18,62 +20,127 @@ pub fn hash_password(password: &[u8]) -> String {
 
 pub async fn register(
     store: Store,
-    account: Account
+    account: NewAccount,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     // Takes the password as a byte array and passes it to the newly created hash function
     let hashed_password = hash_password(account.password.as_bytes());
 
     let account = Account {
-        id: account.id,
+        id: None,
         email: account.email,
         password: hashed_password,
+        role: Role::User,
+        disabled: false,
     };
 
     match store.add_account(account).await {
-        Ok(_) => {
-            Ok(warp::reply::with_status("Account added", StatusCode::OK))
-        },
+        Ok(_) => Ok(warp::reply::with_status("Account added", StatusCode::OK)),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
 
-fn verify_password(
-    hash: &str,
-    password: &[u8]
-) -> Result<bool, argon2::Error> {
+fn verify_password(hash: &str, password: &[u8]) -> Result<bool, argon2::Error> {
     argon2::verify_encoded(hash, password)
 }
 
-fn issue_token(
-    account_id: AccountId
-) -> String {
+fn issue_token(account_id: AccountId, role: Role, config: &AuthConfig) -> String {
     let current_datetime = Utc::now();
-    let dt = current_datetime + chrono::Duration::days(1);
+    let dt = current_datetime + chrono::Duration::hours(config.token_lifetime_hours);
 
     paseto::tokens::PasetoBuilder::new()
-        .set_encryption_key(
-            &Vec::from("RANDOM WORDS WINTER MACINTOSH PC".as_bytes())
-        )
+        .set_encryption_key(&Vec::from(config.keys.current()))
         .set_expiration(&dt)
         .set_not_before(&Utc::now())
         .set_claim("account_id", serde_json::json!(account_id))
+        .set_claim("role", serde_json::json!(role))
         .build()
         .expect("Failed to construct paseto token w/ builder!")
 }
 
+/// Decrypts a token issued by `issue_token` and turns its claims into a `Session`.
+/// Accepts the raw token as well as the `Bearer <token>` form of the header.
+pub fn verify_token(token: &str, config: &AuthConfig) -> Result<Session, handle_errors::Error> {
+    let token = token.strip_prefix("Bearer ").unwrap_or(token).trim();
+
+    // Tokens issued before a key rotation are encrypted with the previous key
+    for key in config.keys.accepted() {
+        // Checks the expiration and not-before claims against the current time
+        if let Ok(claims) = paseto::tokens::validate_local_token(
+            token,
+            None,
+            key,
+            &paseto::tokens::TimeBackend::Chrono,
+        ) {
+            return serde_json::from_value::<Session>(claims)
+                .map_err(|_| handle_errors::Error::CannotDecryptToken);
+        }
+    }
+
+    Err(handle_errors::Error::CannotDecryptToken)
+}
+
+/// Filter for protected routes, extracts the `Session` from the `Authorization` header
+/// and rejects the request if the header is missing or the token is not valid.
+/// Tokens stay valid until they expire, so the account is looked up on every request
+/// to refuse tokens of accounts which got disabled since, and the role of the session
+/// is the current role of the account instead of the one in the token.
+pub fn auth(
+    config: AuthConfig,
+    store: Store,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("Authorization").and_then(move |token: Option<String>| {
+        let session = match token {
+            Some(token) => verify_token(&token, &config),
+            None => Err(handle_errors::Error::Unauthorized),
+        };
+        let store = store.clone();
+        async move {
+            let mut session = session?;
+            match store.get_account_profile(session.account_id.clone()).await {
+                Ok(account) if account.disabled => Err(handle_errors::Error::AccountDisabled),
+                Ok(account) => {
+                    session.role = account.role;
+                    Ok(session)
+                }
+                Err(e) => Err(e),
+            }
+            .map_err(warp::reject::custom)
+        }
+    })
+}
+
+/// Like `auth`, but additionally rejects with `Forbidden` unless the
+/// account has at least the given role, e.g. `require_role(Role::Moderator)`
+pub fn require_role(
+    role: Role,
+    config: AuthConfig,
+    store: Store,
+) -> impl Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(config, store).and_then(move |session: Session| {
+        future::ready(if session.role >= role {
+            Ok(session)
+        } else {
+            Err(warp::reject::custom(handle_errors::Error::Forbidden))
+        })
+    })
+}
+
 pub async fn login(
     store: Store,
-    login: Account
+    config: AuthConfig,
+    login: Account,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     match store.get_account(login.email).await {
-        Ok(account) => match verify_password(
-            &account.password,
-            login.password.as_bytes()
-        ) {
+        Ok(account) => match verify_password(&account.password, login.password.as_bytes()) {
             Ok(verified) => {
-                if verified {
+                // Only tell the owner, who knows the password, that the account is disabled
+                if verified && account.disabled {
+                    Err(warp::reject::custom(handle_errors::Error::AccountDisabled))
+                } else if verified {
                     Ok(warp::reply::json(&issue_token(
                         account.id.expect("id not found"),
+                        account.role,
+                        &config,
                     )))
                 } else {
                     Err(warp::reject::custom(handle_errors::Error::WrongPassword))
@@ -83,6 +150,10 @@ pub async fn login(
                 handle_errors::Error::ArgonLibraryError(e),
             )),
         },
+        // Unknown addresses look like wrong passwords, so nobody can probe for accounts
+        Err(handle_errors::Error::AccountNotFound) => {
+            Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+        }
         Err(e) => Err(warp::reject::custom(e)),
     }
-}
\ No newline at end of file
+}
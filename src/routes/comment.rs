@@ -0,0 +1,202 @@
+use crate::profanity::Moderation;
+use crate::routes::answer::visible_answer;
+use crate::routes::question::visible_question;
+use crate::routes::with_links;
+use crate::store::Store;
+use crate::types::account::Session;
+use crate::types::answer::AnswerId;
+use crate::types::comment::{validate_comment, Comment, CommentTarget, NewComment};
+use crate::types::pagination::Pagination;
+use crate::types::question::QuestionId;
+use tracing::{event, Level};
+use warp::http::header::LOCATION;
+use warp::http::StatusCode;
+use warp::reply::Response;
+
+/// The visible comments on a visible question, `limit` and `offset` page through them
+pub async fn get_question_comments(
+    id: i32,
+    params: Vec<(String, String)>,
+    pagination: Pagination,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    visible_question(&store, id).await?;
+    let path = format!("/questions/{}/comments", id);
+    list_comments(
+        CommentTarget::Question(QuestionId(id)),
+        &path,
+        &params,
+        pagination,
+        &store,
+    )
+    .await
+}
+
+/// The visible comments on a visible answer, `limit` and `offset` page through them
+pub async fn get_answer_comments(
+    id: i32,
+    params: Vec<(String, String)>,
+    pagination: Pagination,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    visible_answer(&store, id).await?;
+    let path = format!("/answers/{}/comments", id);
+    list_comments(
+        CommentTarget::Answer(AnswerId(id)),
+        &path,
+        &params,
+        pagination,
+        &store,
+    )
+    .await
+}
+
+async fn list_comments(
+    target: CommentTarget,
+    path: &str,
+    params: &[(String, String)],
+    pagination: Pagination,
+    store: &Store,
+) -> Result<Response, warp::Rejection> {
+    let pagination = pagination.offset_only()?;
+    // One more than the page shows whether there is a next page
+    match store
+        .get_comments(
+            &target,
+            Some(pagination.limit.saturating_add(1)),
+            pagination.offset,
+        )
+        .await
+    {
+        Ok(mut comments) => {
+            let has_more = pagination.truncate(&mut comments);
+            let links = pagination.links(path, params, has_more, None);
+            Ok(with_links(warp::reply::json(&comments), links))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn add_question_comment(
+    id: i32,
+    session: Session,
+    store: Store,
+    moderation: Moderation,
+    comment: NewComment,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    visible_question(&store, id).await?;
+    add_comment(
+        CommentTarget::Question(QuestionId(id)),
+        session,
+        &store,
+        &moderation,
+        comment,
+    )
+    .await
+}
+
+pub async fn add_answer_comment(
+    id: i32,
+    session: Session,
+    store: Store,
+    moderation: Moderation,
+    comment: NewComment,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    visible_answer(&store, id).await?;
+    add_comment(
+        CommentTarget::Answer(AnswerId(id)),
+        session,
+        &store,
+        &moderation,
+        comment,
+    )
+    .await
+}
+
+/// Comments go through the same moderation as questions and answers
+async fn add_comment(
+    target: CommentTarget,
+    session: Session,
+    store: &Store,
+    moderation: &Moderation,
+    comment: NewComment,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    validate_comment(&comment.content)?;
+    let content = match moderation.moderate(comment.content).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let comment = NewComment {
+        content: content.content,
+    };
+    match store
+        .add_comment(comment, target, session.account_id, content.status)
+        .await
+    {
+        Ok(comment) => Ok(warp::reply::with_status(
+            warp::reply::with_header(
+                warp::reply::json(&comment),
+                LOCATION,
+                format!("/comments/{}", comment.id.0),
+            ),
+            StatusCode::CREATED,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn update_comment(
+    id: i32,
+    session: Session,
+    store: Store,
+    moderation: Moderation,
+    update: NewComment,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        comment_id = id,
+        "updating comment"
+    );
+    let comment = match store.get_comment(id).await {
+        Ok(comment) => comment,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    // Only the author's edits are worth a moderation call
+    store.ensure_comment_owner(id, &session).await?;
+    validate_comment(&update.content)?;
+    let content = match moderation.moderate(update.content).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let comment = Comment {
+        content: content.content,
+        status: content.status,
+        ..comment
+    };
+    match store.update_comment(comment, id, &session).await {
+        Ok(res) => Ok(warp::reply::json(&res)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn delete_comment(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        comment_id = id,
+        "deleting comment"
+    );
+    match store.delete_comment(id, &session).await {
+        Ok(_) => Ok(warp::reply::with_status(
+            format!("Comment {} deleted", id),
+            StatusCode::OK,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
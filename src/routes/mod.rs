@@ -1,3 +1,507 @@
+use crate::config::{AuthConfig, PaginationConfig};
+use crate::profanity::cache::CachedChecker;
+use crate::profanity::Moderation;
+use crate::store::Store;
+use crate::types::account::Role;
+use crate::types::pagination::{extract_pagination, Pagination};
+use authentication::{auth, require_role};
+use handle_errors::return_error;
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use warp::http::header::{HeaderValue, LINK};
+use warp::reply::Response;
+use warp::{http::Method, Filter, Rejection, Reply};
+
+pub mod admin;
 pub mod answer;
-pub mod question;
 pub mod authentication;
+pub mod comment;
+pub mod moderation;
+pub mod question;
+pub mod transfer;
+
+#[cfg(test)]
+mod tests;
+
+/// Reads the body as JSON, or as a form if the `Content-Type` says so
+fn json_or_form<T: DeserializeOwned + Send>(
+) -> impl Filter<Extract = (T,), Error = Rejection> + Clone {
+    warp::body::json().or(warp::body::form()).unify()
+}
+
+/// The pagination of the query string, refused with a 400 naming the problem
+fn pagination(
+    config: PaginationConfig,
+) -> impl Filter<Extract = (Pagination,), Error = Rejection> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and_then(move |query: String| async move {
+            extract_pagination(&query, &config).map_err(warp::reject::custom)
+        })
+}
+
+/// Adds the `Link` header of a page, if there are other pages
+fn with_links(reply: impl Reply, links: Option<String>) -> Response {
+    let mut response = reply.into_response();
+    if let Some(links) = links.and_then(|links| HeaderValue::from_str(&links).ok()) {
+        response.headers_mut().insert(LINK, links);
+    }
+    response
+}
+
+/// Builds every route of the server, independent of where the store and the checkers come from
+pub fn router(
+    store: Store,
+    moderation: Moderation,
+    cache: Arc<CachedChecker>,
+    auth_config: AuthConfig,
+    pagination_config: PaginationConfig,
+) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let filter_store = store.clone();
+    let store_filter = warp::any().map(move || filter_store.clone());
+    let login_config = auth_config.clone();
+    let auth_config_filter = warp::any().map(move || login_config.clone());
+    let cache_filter = warp::any().map(move || cache.clone());
+    let moderation_filter = warp::any().map(move || moderation.clone());
+
+    let cors = warp::cors()
+        .allow_any_origin()
+        .allow_header("content-type")
+        .allow_header("authorization")
+        .allow_methods(&[Method::PUT, Method::DELETE, Method::GET, Method::POST]);
+
+    let get_questions = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination(pagination_config))
+        .and(store_filter.clone())
+        .and_then(question::get_questions)
+        .with(warp::trace(|info| {
+            tracing::info_span!(
+                "get_questions request",
+                method=%info.method(),
+                path=%info.path(),
+                id = %uuid::Uuid::new_v4(),
+            )
+        }));
+
+    let get_question = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination(pagination_config))
+        .and(store_filter.clone())
+        .and_then(question::get_question);
+
+    let get_answers = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination(pagination_config))
+        .and(store_filter.clone())
+        .and_then(answer::get_answers);
+
+    let get_tags = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(question::get_tags);
+
+    let search = warp::get()
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination(pagination_config))
+        .and(store_filter.clone())
+        .and_then(question::search);
+
+    let add_question = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(warp::body::json())
+        .and_then(question::add_question);
+
+    let update_question = warp::put()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(warp::body::json())
+        .and_then(question::update_question);
+
+    let delete_question = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and_then(question::delete_question);
+
+    let add_answer = warp::post()
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(json_or_form())
+        .and_then(answer::add_answer);
+
+    let get_answer = warp::get()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and_then(answer::get_answer);
+
+    let update_answer = warp::put()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(json_or_form())
+        .and_then(answer::update_answer);
+
+    let delete_answer = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and_then(answer::delete_answer);
+
+    let vote_question = warp::put()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(json_or_form())
+        .and_then(question::vote_question);
+
+    let retract_question_vote = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and_then(question::retract_question_vote);
+
+    let vote_answer = warp::put()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(json_or_form())
+        .and_then(answer::vote_answer);
+
+    let retract_answer_vote = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and_then(answer::retract_answer_vote);
+
+    let accept_answer = warp::put()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accepted_answer"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(json_or_form())
+        .and_then(question::accept_answer);
+
+    let unaccept_answer = warp::delete()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accepted_answer"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and_then(question::unaccept_answer);
+
+    let get_question_comments = warp::get()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination(pagination_config))
+        .and(store_filter.clone())
+        .and_then(comment::get_question_comments);
+
+    let get_answer_comments = warp::get()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(pagination(pagination_config))
+        .and(store_filter.clone())
+        .and_then(comment::get_answer_comments);
+
+    let add_question_comment = warp::post()
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(json_or_form())
+        .and_then(comment::add_question_comment);
+
+    let add_answer_comment = warp::post()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(json_or_form())
+        .and_then(comment::add_answer_comment);
+
+    let update_comment = warp::put()
+        .and(warp::path("comments"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(json_or_form())
+        .and_then(comment::update_comment);
+
+    let delete_comment = warp::delete()
+        .and(warp::path("comments"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(auth(auth_config.clone(), store.clone()))
+        .and(store_filter.clone())
+        .and_then(comment::delete_comment);
+
+    let get_pending_comments = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Moderator,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(moderation::get_pending_comments);
+
+    let review_comment = warp::put()
+        .and(warp::path("moderation"))
+        .and(warp::path("comments"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Moderator,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(moderation::review_comment);
+
+    // Routes of one feature get combined first, which keeps the type of the whole
+    // combination shallow enough for the compiler
+    let votes = vote_question
+        .or(retract_question_vote)
+        .or(vote_answer)
+        .or(retract_answer_vote);
+    let acceptance = accept_answer.or(unaccept_answer);
+    let comments = get_question_comments
+        .or(get_answer_comments)
+        .or(add_question_comment)
+        .or(add_answer_comment)
+        .or(update_comment)
+        .or(delete_comment)
+        .or(get_pending_comments)
+        .or(review_comment);
+
+    let registration = warp::post()
+        .and(warp::path("registration"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(authentication::register);
+
+    let login = warp::post()
+        .and(warp::path("login"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(auth_config_filter.clone())
+        .and(warp::body::json())
+        .and_then(authentication::login);
+
+    let get_accounts = warp::get()
+        .and(warp::path("admin"))
+        .and(warp::path("accounts"))
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Admin,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(admin::get_accounts);
+
+    let update_role = warp::put()
+        .and(warp::path("admin"))
+        .and(warp::path("accounts"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("role"))
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Admin,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(admin::update_role);
+
+    let get_pending_questions = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Moderator,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(moderation::get_pending_questions);
+
+    let get_pending_answers = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Moderator,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(moderation::get_pending_answers);
+
+    let review_question = warp::put()
+        .and(warp::path("moderation"))
+        .and(warp::path("questions"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Moderator,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(moderation::review_question);
+
+    let review_answer = warp::put()
+        .and(warp::path("moderation"))
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Moderator,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(moderation::review_answer);
+
+    let get_cache_stats = warp::get()
+        .and(warp::path("moderation"))
+        .and(warp::path("cache"))
+        .and(warp::path::end())
+        .and(require_role(
+            Role::Moderator,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(cache_filter)
+        .and_then(moderation::get_cache_stats);
+
+    let export = warp::get()
+        .and(warp::path("admin"))
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(require_role(
+            Role::Admin,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and_then(transfer::export);
+
+    let import = warp::post()
+        .and(warp::path("admin"))
+        .and(warp::path("import"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(require_role(
+            Role::Admin,
+            auth_config.clone(),
+            store.clone(),
+        ))
+        .and(store_filter.clone())
+        .and(moderation_filter.clone())
+        .and(warp::body::content_length_limit(transfer::MAX_IMPORT_BYTES))
+        .and(warp::body::bytes())
+        .and_then(transfer::import);
+
+    get_questions
+        .or(get_question)
+        .or(get_answers)
+        .or(search)
+        .or(get_tags)
+        .or(add_question)
+        .or(update_question)
+        .or(delete_question)
+        .or(add_answer)
+        .or(get_answer)
+        .or(update_answer)
+        .or(delete_answer)
+        .or(votes)
+        .or(acceptance)
+        .or(comments)
+        .or(registration)
+        .or(login)
+        .or(get_accounts)
+        .or(update_role)
+        .or(get_pending_questions)
+        .or(get_pending_answers)
+        .or(review_question)
+        .or(review_answer)
+        .or(get_cache_stats)
+        .or(export)
+        .or(import)
+        .with(cors)
+        .with(warp::trace::request())
+        .recover(return_error)
+}
@@ -0,0 +1,121 @@
+use crate::profanity::cache::CachedChecker;
+use crate::store::Store;
+use crate::types::account::Session;
+use crate::types::moderation::ModerationStatus;
+use std::sync::Arc;
+use tracing::{event, Level};
+
+pub async fn get_pending_questions(
+    _session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store
+        .get_questions_by_status(ModerationStatus::Pending)
+        .await
+    {
+        Ok(questions) => Ok(warp::reply::json(&questions)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn get_pending_answers(
+    _session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_answers_by_status(ModerationStatus::Pending).await {
+        Ok(answers) => Ok(warp::reply::json(&answers)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn get_pending_comments(
+    _session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store
+        .get_comments_by_status(ModerationStatus::Pending)
+        .await
+    {
+        Ok(comments) => Ok(warp::reply::json(&comments)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Publishes (`approve`) or hides (`reject`) a queued question
+pub async fn review_question(
+    id: i32,
+    decision: String,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let status = decision_status(&decision)?;
+    event!(
+        Level::INFO,
+        moderator_id = session.account_id.0,
+        question_id = id,
+        status = status.as_str(),
+        "reviewing question"
+    );
+    match store.set_question_status(id, status).await {
+        Ok(question) => Ok(warp::reply::json(&question)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Publishes (`approve`) or hides (`reject`) a queued answer
+pub async fn review_answer(
+    id: i32,
+    decision: String,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let status = decision_status(&decision)?;
+    event!(
+        Level::INFO,
+        moderator_id = session.account_id.0,
+        answer_id = id,
+        status = status.as_str(),
+        "reviewing answer"
+    );
+    match store.set_answer_status(id, status).await {
+        Ok(answer) => Ok(warp::reply::json(&answer)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Publishes (`approve`) or hides (`reject`) a queued comment
+pub async fn review_comment(
+    id: i32,
+    decision: String,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let status = decision_status(&decision)?;
+    event!(
+        Level::INFO,
+        moderator_id = session.account_id.0,
+        comment_id = id,
+        status = status.as_str(),
+        "reviewing comment"
+    );
+    match store.set_comment_status(id, status).await {
+        Ok(comment) => Ok(warp::reply::json(&comment)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+fn decision_status(decision: &str) -> Result<ModerationStatus, warp::Rejection> {
+    match decision {
+        "approve" => Ok(ModerationStatus::Published),
+        "reject" => Ok(ModerationStatus::Rejected),
+        _ => Err(warp::reject::not_found()),
+    }
+}
+
+/// Hit and miss counters of the profanity check cache
+pub async fn get_cache_stats(
+    _session: Session,
+    cache: Arc<CachedChecker>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&cache.stats()))
+}
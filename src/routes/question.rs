@@ -1,13 +1,32 @@
+use crate::profanity::Moderation;
+use crate::routes::answer::visible_answer;
+use crate::routes::with_links;
 use crate::store::Store;
-use crate::types::pagination::{extract_pagination, Pagination};
-use crate::types::question::{NewQuestion, Question};
-use std::collections::HashMap;
+use crate::types::account::Session;
+use crate::types::answer::{extract_answer_sort, AcceptAnswer};
+use crate::types::pagination::Pagination;
+use crate::types::question::{
+    extract_question_filter, NewQuestion, Question, QuestionDetail, QuestionUpdate,
+};
+use crate::types::search::extract_search;
+use crate::types::tag::normalize_tags;
+use crate::types::vote::NewVote;
+use handle_errors::Error;
 use tracing::{event, instrument, Level};
 use warp::http::StatusCode;
-use crate::profanity::check_profanity;
 
-pub async fn delete_question(id: i32, store: Store) -> Result<impl warp::Reply, warp::Rejection> {
-    match store.delete_question(id).await {
+pub async fn delete_question(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        question_id = id,
+        "deleting question"
+    );
+    match store.delete_question(id, &session).await {
         Ok(_) => Ok(warp::reply::with_status(
             format!("Question {} deleted", id),
             StatusCode::OK,
@@ -18,60 +37,74 @@ pub async fn delete_question(id: i32, store: Store) -> Result<impl warp::Reply,
 
 pub async fn update_question(
     id: i32,
+    session: Session,
     store: Store,
-    question: Question,
+    moderation: Moderation,
+    update: QuestionUpdate,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    // let title = match check_profanity(question.title).await {
-    //     Ok(res) => res,
-    //     Err(e) => return Err(warp::reject::custom(e)),
-    // };
-    let title = tokio::spawn(check_profanity(question.title));
-    let content = tokio::spawn(check_profanity(question.content));
-
-    let (title, content) = (title.await.unwrap(), content.await.unwrap());
-
-    if title.is_err() {
-        return Err(warp::reject::custom(title.unwrap_err()));
-    }
-
-    if content.is_err() {
-        return Err(warp::reject::custom(content.unwrap_err()));
-    }
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        question_id = id,
+        "updating question"
+    );
+    let question = match store.get_question(id).await {
+        Ok(question) => question,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    // Only the author's edits are worth a moderation call
+    store.ensure_question_owner(id, &session).await?;
+    let tags = normalize_tags(update.tags)?;
+    let (title, content) = match tokio::join!(
+        moderation.moderate(update.title),
+        moderation.moderate(update.content),
+    ) {
+        (Ok(title), Ok(content)) => (title, content),
+        (Err(e), _) | (_, Err(e)) => return Err(warp::reject::custom(e)),
+    };
 
     let question = Question {
-      id: question.id,
-        title: title.unwrap(),
-        content: content.unwrap(),
-        tags: question.tags
+        title: title.content,
+        content: content.content,
+        tags,
+        status: title.status.and(content.status),
+        ..question
     };
 
-    match store.update_question(question, id).await {
+    match store.update_question(question, id, &session).await {
         Ok(res) => Ok(warp::reply::json(&res)),
         Err(e) => Err(warp::reject::custom(e)),
     }
 }
 
 pub async fn add_question(
+    session: Session,
     store: Store,
+    moderation: Moderation,
     question: NewQuestion,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let title = match check_profanity(question.title).await {
+    let tags = normalize_tags(question.tags)?;
+    let title = match moderation.moderate(question.title).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
-    let content = match check_profanity(question.content).await {
+    let content = match moderation.moderate(question.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
+    let status = title.status.and(content.status);
     let question = NewQuestion {
-        title,
-        content,
-        tags: question.tags,
+        title: title.content,
+        content: content.content,
+        tags,
     };
 
-    match store.add_question(question).await {
+    match store
+        .add_question(question, session.account_id, status)
+        .await
+    {
         // return a proper question back to the client instead of just a string and HTTP code
         Ok(question) => Ok(warp::reply::json(&question)),
         Err(e) => Err(warp::reject::custom(e)),
@@ -80,22 +113,215 @@ pub async fn add_question(
 
 #[instrument]
 pub async fn get_questions(
-    params: HashMap<String, String>,
+    params: Vec<(String, String)>,
+    pagination: Pagination,
     store: Store,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     event!(target: "practical_rust_book", Level::INFO, "querying questions");
-    let mut pagination = Pagination::default();
+    let filter = extract_question_filter(&params)?;
+    event!(
+        Level::INFO,
+        limit = pagination.limit,
+        offset = pagination.offset,
+        keyset = pagination.keyset
+    );
 
-    if !params.is_empty() {
-        event!(Level::INFO, pagination = true);
-        pagination = extract_pagination(params)?;
+    if pagination.keyset {
+        if let Some(after) = &pagination.after {
+            if after.key.is_some() != filter.sort.has_key() {
+                return Err(warp::reject::custom(Error::InvalidParameter(
+                    "after is a cursor of another sort".to_string(),
+                )));
+            }
+        }
+        let mut page = store
+            .get_question_page(&filter, pagination.after.as_ref(), pagination.limit)
+            .await?;
+        if pagination.total {
+            page.total = Some(store.count_questions(&filter).await?);
+        }
+        let links = pagination.links(
+            "/questions",
+            &params,
+            page.has_more,
+            page.next_cursor.as_ref(),
+        );
+        return Ok(with_links(warp::reply::json(&page), links));
     }
 
+    // One more than the page shows whether there is a next page
     match store
-        .get_questions(pagination.limit, pagination.offset)
+        .get_questions(
+            &filter,
+            Some(pagination.limit.saturating_add(1)),
+            pagination.offset,
+        )
         .await
     {
-        Ok(res) => Ok(warp::reply::json(&res)),
+        Ok(mut questions) => {
+            let has_more = pagination.truncate(&mut questions);
+            let links = pagination.links("/questions", &params, has_more, None);
+            Ok(with_links(warp::reply::json(&questions), links))
+        }
         Err(e) => return Err(warp::reject::custom(e)),
     }
 }
+
+/// A visible question with its answers, `limit` and `offset` page through the answers,
+/// `sort` orders them
+pub async fn get_question(
+    id: i32,
+    params: Vec<(String, String)>,
+    pagination: Pagination,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let pagination = pagination.offset_only()?;
+    let sort = extract_answer_sort(&params)?;
+    let question = visible_question(&store, id).await?;
+    match store
+        .get_answers(id, sort, Some(pagination.limit), pagination.offset)
+        .await
+    {
+        Ok(answers) => Ok(warp::reply::json(&QuestionDetail { question, answers })),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Hidden questions are not found, the same as questions which do not exist
+pub async fn visible_question(store: &Store, id: i32) -> Result<Question, warp::Rejection> {
+    match store.get_question(id).await {
+        Ok(question) if question.status.is_visible() => Ok(question),
+        Ok(_) => Err(warp::reject::custom(Error::QuestionNotFound)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Sets the vote of the account on a visible question
+pub async fn vote_question(
+    id: i32,
+    session: Session,
+    store: Store,
+    vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        question_id = id,
+        "voting on question"
+    );
+    visible_question(&store, id).await?;
+    match store
+        .vote_question(id, session.account_id, Some(vote.value))
+        .await
+    {
+        Ok(question) => Ok(warp::reply::json(&question)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Takes back the vote of the account on a visible question
+pub async fn retract_question_vote(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        question_id = id,
+        "retracting question vote"
+    );
+    visible_question(&store, id).await?;
+    match store.vote_question(id, session.account_id, None).await {
+        Ok(question) => Ok(warp::reply::json(&question)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Marks a visible answer to the question as accepted
+pub async fn accept_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+    accept: AcceptAnswer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        question_id = id,
+        "accepting answer"
+    );
+    let question = visible_question(&store, id).await?;
+    ensure_author(&question, &session)?;
+    let answer = visible_answer(&store, accept.answer_id.0).await?;
+    if answer.question_id != question.id {
+        return Err(warp::reject::custom(Error::AnswerOfAnotherQuestion));
+    }
+    match store.set_accepted_answer(id, Some(answer.id.0)).await {
+        Ok(question) => Ok(warp::reply::json(&question)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Takes back the acceptance of an answer to a visible question
+pub async fn unaccept_answer(
+    id: i32,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        account_id = session.account_id.0,
+        question_id = id,
+        "unaccepting answer"
+    );
+    let question = visible_question(&store, id).await?;
+    ensure_author(&question, &session)?;
+    match store.set_accepted_answer(id, None).await {
+        Ok(question) => Ok(warp::reply::json(&question)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Only the author of a question decides which answer it accepts, moderators do not
+fn ensure_author(question: &Question, session: &Session) -> Result<(), warp::Rejection> {
+    match &question.account_id {
+        Some(author) if *author == session.account_id => Ok(()),
+        _ => Err(warp::reject::custom(Error::Forbidden)),
+    }
+}
+
+/// Searches the visible questions and answers, `limit` and `offset` page through the matches
+pub async fn search(
+    params: Vec<(String, String)>,
+    pagination: Pagination,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let query = extract_search(&params.iter().cloned().collect())?;
+    let pagination = pagination.offset_only()?;
+
+    event!(Level::INFO, q = %query.q, "searching questions");
+    match store
+        .search(
+            &query,
+            Some(pagination.limit.saturating_add(1)),
+            pagination.offset,
+        )
+        .await
+    {
+        Ok(mut hits) => {
+            let has_more = pagination.truncate(&mut hits);
+            let links = pagination.links("/search", &params, has_more, None);
+            Ok(with_links(warp::reply::json(&hits), links))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// The tags of the visible questions, the most used first
+pub async fn get_tags(store: Store) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_tags().await {
+        Ok(tags) => Ok(warp::reply::json(&tags)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
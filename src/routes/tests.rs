@@ -0,0 +1,381 @@
+//! Requests against the whole `router`, backed by the memory store and a checker
+//! which accepts everything, so these only test what the routes add on top of the store.
+
+use crate::config::{AuthConfig, FailurePolicy, ModerationPolicy, PaginationConfig};
+use crate::profanity::cache::CachedChecker;
+use crate::profanity::{Moderation, NoopChecker};
+use crate::routes::authentication::hash_password;
+use crate::routes::router;
+use crate::secrets::{PasetoKeys, Secret};
+use crate::store::memory::MemoryStore;
+use crate::store::Store;
+use crate::types::account::{Account, Role};
+use crate::types::answer::AnswerSort;
+use crate::types::moderation::ModerationStatus;
+use crate::types::question::{NewQuestion, Question, QuestionUpdate};
+use std::sync::Arc;
+use std::time::Duration;
+use warp::http::header::LINK;
+use warp::http::StatusCode;
+use warp::{Filter, Rejection, Reply};
+
+fn routes(store: Store) -> impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone {
+    let checker = Arc::new(NoopChecker);
+    let cache = Arc::new(CachedChecker::new(checker.clone(), 0, Duration::ZERO));
+    let moderation = Moderation::new(checker, ModerationPolicy::Censor, FailurePolicy::FailClosed);
+    let auth_config = AuthConfig {
+        keys: PasetoKeys::new(
+            Secret::from("0123456789abcdef0123456789abcdef".to_string()),
+            None,
+        ),
+        ..AuthConfig::default()
+    };
+    router(
+        store,
+        moderation,
+        cache,
+        auth_config,
+        PaginationConfig::default(),
+    )
+}
+
+/// Adds an account and logs it in, returns its `Authorization` header
+async fn login(
+    store: &Store,
+    routes: &(impl Filter<Extract = (impl Reply,), Error = Rejection> + Clone + 'static),
+    email: &str,
+    role: Role,
+) -> String {
+    store
+        .add_account(Account {
+            id: None,
+            email: email.to_string(),
+            password: hash_password(b"secret"),
+            role,
+            disabled: false,
+        })
+        .await
+        .unwrap();
+    let res = warp::test::request()
+        .method("POST")
+        .path("/login")
+        .json(&serde_json::json!({ "email": email, "password": "secret" }))
+        .reply(routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let token: String = serde_json::from_slice(res.body()).unwrap();
+    format!("Bearer {}", token)
+}
+
+fn new_question(title: &str) -> NewQuestion {
+    NewQuestion {
+        title: title.to_string(),
+        content: "How does it work?".to_string(),
+        tags: None,
+    }
+}
+
+#[tokio::test]
+async fn writing_needs_a_token() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/questions")
+        .json(&new_question("Why?"))
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/questions")
+        .header("Authorization", "Bearer nonsense")
+        .json(&new_question("Why?"))
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn only_the_author_changes_a_question() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store.clone());
+    let author = login(&store, &routes, "author@example.com", Role::User).await;
+    let other = login(&store, &routes, "other@example.com", Role::User).await;
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/questions")
+        .header("Authorization", &author)
+        .json(&new_question("Why?"))
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let question: Question = serde_json::from_slice(res.body()).unwrap();
+    let update = QuestionUpdate {
+        title: "Why not?".to_string(),
+        content: question.content.clone(),
+        tags: None,
+    };
+
+    let path = format!("/questions/{}", question.id.0);
+    let res = warp::test::request()
+        .method("PUT")
+        .path(&path)
+        .header("Authorization", &other)
+        .json(&update)
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let res = warp::test::request()
+        .method("DELETE")
+        .path(&path)
+        .header("Authorization", &other)
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = warp::test::request()
+        .method("PUT")
+        .path(&path)
+        .header("Authorization", &author)
+        .json(&update)
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let updated: Question = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(updated.title, "Why not?");
+}
+
+#[tokio::test]
+async fn hidden_questions_are_not_found() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store.clone());
+    store
+        .add_account(Account {
+            id: None,
+            email: "author@example.com".to_string(),
+            password: String::new(),
+            role: Role::User,
+            disabled: false,
+        })
+        .await
+        .unwrap();
+    let author = store
+        .get_account("author@example.com".to_string())
+        .await
+        .unwrap();
+    let author = author.id.unwrap();
+
+    for status in [ModerationStatus::Pending, ModerationStatus::Rejected] {
+        let question = store
+            .add_question(new_question("Why?"), author.clone(), status)
+            .await
+            .unwrap();
+        let res = warp::test::request()
+            .path(&format!("/questions/{}", question.id.0))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let res = warp::test::request()
+            .path(&format!("/questions/{}/answers", question.id.0))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    let res = warp::test::request()
+        .path("/questions")
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let questions: Vec<Question> = serde_json::from_slice(res.body()).unwrap();
+    assert!(questions.is_empty());
+}
+
+#[tokio::test]
+async fn pages_link_to_each_other() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store.clone());
+    let author = login(&store, &routes, "author@example.com", Role::User).await;
+    for title in ["One?", "Two?", "Three?"] {
+        let res = warp::test::request()
+            .method("POST")
+            .path("/questions")
+            .header("Authorization", &author)
+            .json(&new_question(title))
+            .reply(&routes)
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    let link = |res: &warp::http::Response<warp::hyper::body::Bytes>| {
+        res.headers()
+            .get(LINK)
+            .map(|link| link.to_str().unwrap().to_string())
+    };
+
+    let res = warp::test::request()
+        .path("/questions?limit=2")
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        link(&res).unwrap(),
+        "</questions?limit=2&offset=2>; rel=\"next\""
+    );
+
+    let res = warp::test::request()
+        .path("/questions?limit=2&offset=2")
+        .reply(&routes)
+        .await;
+    assert_eq!(
+        link(&res).unwrap(),
+        "</questions?limit=2&offset=0>; rel=\"prev\""
+    );
+
+    // Cursor pages only link forward, with the cursor of the last question
+    let res = warp::test::request()
+        .path("/questions?limit=2&after=")
+        .reply(&routes)
+        .await;
+    let next = link(&res).unwrap();
+    assert!(next.starts_with("</questions?limit=2&after="));
+    assert!(next.ends_with(">; rel=\"next\""));
+    let next = next
+        .trim_start_matches('<')
+        .split('>')
+        .next()
+        .unwrap()
+        .to_string();
+    let res = warp::test::request().path(&next).reply(&routes).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(link(&res), None);
+
+    let res = warp::test::request()
+        .path("/questions?limit=5")
+        .reply(&routes)
+        .await;
+    assert_eq!(link(&res), None);
+}
+
+#[tokio::test]
+async fn tokens_of_disabled_accounts_are_refused() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store.clone());
+    let author = login(&store, &routes, "author@example.com", Role::User).await;
+    let account = store
+        .get_account("author@example.com".to_string())
+        .await
+        .unwrap();
+    store
+        .set_account_disabled(account.id.unwrap(), true)
+        .await
+        .unwrap();
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/questions")
+        .header("Authorization", &author)
+        .json(&new_question("Why?"))
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn demoted_accounts_lose_their_role_right_away() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store.clone());
+    let moderator = login(&store, &routes, "moderator@example.com", Role::Moderator).await;
+
+    let res = warp::test::request()
+        .path("/moderation/questions")
+        .header("Authorization", &moderator)
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let account = store
+        .get_account("moderator@example.com".to_string())
+        .await
+        .unwrap();
+    store
+        .update_account_role(account.id.unwrap(), Role::User)
+        .await
+        .unwrap();
+    let res = warp::test::request()
+        .path("/moderation/questions")
+        .header("Authorization", &moderator)
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn hidden_questions_take_no_answers() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store.clone());
+    let author = login(&store, &routes, "author@example.com", Role::User).await;
+    let account = store
+        .get_account("author@example.com".to_string())
+        .await
+        .unwrap();
+    let question = store
+        .add_question(
+            new_question("Why?"),
+            account.id.unwrap(),
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+
+    let res = warp::test::request()
+        .method("POST")
+        .path("/answers")
+        .header("Authorization", &author)
+        .json(&serde_json::json!({ "content": "Like this", "question_id": question.id.0 }))
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    assert!(store
+        .get_answers(question.id.0, AnswerSort::Oldest, None, 0)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn new_comments_point_to_themselves() {
+    let store: Store = Arc::new(MemoryStore::new());
+    let routes = routes(store.clone());
+    let author = login(&store, &routes, "author@example.com", Role::User).await;
+    let account = store
+        .get_account("author@example.com".to_string())
+        .await
+        .unwrap();
+    let question = store
+        .add_question(
+            new_question("Why?"),
+            account.id.unwrap(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+
+    let res = warp::test::request()
+        .method("POST")
+        .path(&format!("/questions/{}/comments", question.id.0))
+        .header("Authorization", &author)
+        .json(&serde_json::json!({ "content": "Which version?" }))
+        .reply(&routes)
+        .await;
+    assert_eq!(res.status(), StatusCode::CREATED);
+    let comment: serde_json::Value = serde_json::from_slice(res.body()).unwrap();
+    assert_eq!(
+        res.headers()["Location"],
+        format!("/comments/{}", comment["id"])
+    );
+}
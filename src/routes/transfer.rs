@@ -0,0 +1,83 @@
+use crate::profanity::Moderation;
+use crate::store::Store;
+use crate::transfer::{self, ExportFormat, ImportFormat};
+use crate::types::account::Session;
+use handle_errors::Error;
+use serde::Deserialize;
+use tracing::{event, Level};
+use warp::http::header::CONTENT_TYPE;
+use warp::hyper::body::{Body, Bytes};
+
+/// Largest file the import endpoint accepts, bigger ones go through the admin CLI
+pub const MAX_IMPORT_BYTES: u64 = 32 * 1024 * 1024;
+
+#[derive(Deserialize, Debug)]
+pub struct ExportParams {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ImportParams {
+    #[serde(default)]
+    pub format: ImportFormat,
+    /// Check the imported text with the moderation of the server
+    #[serde(default)]
+    pub moderate: bool,
+}
+
+/// Streams every question with its answers, as JSON Lines or CSV
+pub async fn export(
+    params: ExportParams,
+    session: Session,
+    store: Store,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    event!(
+        Level::INFO,
+        admin_id = session.account_id.0,
+        format = params.format.content_type(),
+        "exporting questions"
+    );
+    let body = Body::wrap_stream(transfer::export(store, params.format));
+    Ok(warp::reply::with_header(
+        warp::reply::Response::new(body),
+        CONTENT_TYPE,
+        params.format.content_type(),
+    ))
+}
+
+/// Adds the questions and answers of the body, which belong to the importing admin afterwards
+pub async fn import(
+    params: ImportParams,
+    session: Session,
+    store: Store,
+    moderation: Moderation,
+    body: Bytes,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let input = std::str::from_utf8(&body)
+        .map_err(|e| warp::reject::custom(Error::InvalidImport(e.to_string())))?;
+    let moderation = params.moderate.then_some(&moderation);
+
+    match transfer::import(
+        &store,
+        moderation,
+        session.account_id.clone(),
+        params.format,
+        input,
+    )
+    .await
+    {
+        Ok(report) => {
+            event!(
+                Level::INFO,
+                admin_id = session.account_id.0,
+                questions = report.questions,
+                answers = report.answers,
+                errors = report.errors.len(),
+                "imported questions"
+            );
+            Ok(warp::reply::json(&report))
+        }
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
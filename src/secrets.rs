@@ -0,0 +1,179 @@
+use chrono::prelude::*;
+use std::fmt::Formatter;
+use std::path::{Path, PathBuf};
+
+/// Length in bytes of the symmetric key PASETO v2 local tokens are encrypted with
+const PASETO_KEY_LENGTH: usize = 32;
+
+/// Names of the secrets the server needs. Each one is looked up as an
+/// environment variable, then as a file named by `<NAME>_FILE`, and at last as a
+/// lowercase file in the secrets directory (e.g. `/run/secrets/paseto_key`)
+pub const PASETO_KEY: &str = "PASETO_KEY";
+pub const PASETO_PREVIOUS_KEY: &str = "PASETO_PREVIOUS_KEY";
+/// When tokens encrypted with the previous key stop being accepted, e.g. `2025-01-31T12:00:00Z`.
+/// An absolute moment, so restarting the server does not keep the previous key alive.
+pub const PASETO_PREVIOUS_KEY_VALID_UNTIL: &str = "PASETO_PREVIOUS_KEY_VALID_UNTIL";
+pub const APILAYER_API_KEY: &str = "APILAYER_API_KEY";
+
+/// A value which must not end up in logs, `Debug` only prints a placeholder
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Secret(value)
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Secret(***)")
+    }
+}
+
+/// The keys tokens are encrypted with. New tokens always use the current key,
+/// tokens encrypted with the previous key are still accepted until the
+/// moment given with it is over
+#[derive(Clone, Debug, Default)]
+pub struct PasetoKeys {
+    current: Secret,
+    previous: Option<(Secret, DateTime<Utc>)>,
+}
+
+impl PasetoKeys {
+    pub fn new(current: Secret, previous: Option<(Secret, DateTime<Utc>)>) -> Self {
+        PasetoKeys { current, previous }
+    }
+
+    pub fn current(&self) -> &[u8] {
+        self.current.expose().as_bytes()
+    }
+
+    /// Keys a token may be encrypted with, the current one first
+    pub fn accepted(&self) -> Vec<&[u8]> {
+        let mut keys = vec![self.current()];
+        if let Some((key, valid_until)) = &self.previous {
+            if Utc::now() < *valid_until {
+                keys.push(key.expose().as_bytes());
+            }
+        }
+        keys
+    }
+}
+
+#[derive(Debug)]
+pub enum SecretError {
+    Missing(String),
+    CannotReadFile(String, PathBuf, std::io::Error),
+    InvalidPasetoKey(String, usize),
+    InvalidTimestamp(String, chrono::ParseError),
+}
+
+impl std::fmt::Display for SecretError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretError::Missing(name) => write!(
+                f,
+                "Secret {} is missing, set {} or {}_FILE or put it into the secrets directory",
+                name, name, name
+            ),
+            SecretError::CannotReadFile(name, path, err) => {
+                write!(
+                    f,
+                    "Cannot read secret {} from {}: {}",
+                    name,
+                    path.display(),
+                    err
+                )
+            }
+            SecretError::InvalidPasetoKey(name, len) => write!(
+                f,
+                "Secret {} must be exactly {} bytes long, got {}",
+                name, PASETO_KEY_LENGTH, len
+            ),
+            SecretError::InvalidTimestamp(name, err) => {
+                write!(f, "Secret {} must be an RFC 3339 timestamp: {}", name, err)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+/// Reads secrets from the environment or from files,
+/// like the ones Docker and Kubernetes mount into a container
+#[derive(Debug, Clone, Default)]
+pub struct SecretsProvider {
+    dir: Option<PathBuf>,
+}
+
+impl SecretsProvider {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        SecretsProvider { dir }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<Secret>, SecretError> {
+        if let Ok(value) = std::env::var(name) {
+            return Ok(Some(Secret(value)));
+        }
+        if let Ok(path) = std::env::var(format!("{}_FILE", name)) {
+            return read_secret_file(name, Path::new(&path)).map(Some);
+        }
+        if let Some(dir) = &self.dir {
+            let path = dir.join(name.to_lowercase());
+            if path.exists() {
+                return read_secret_file(name, &path).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn require(&self, name: &str) -> Result<Secret, SecretError> {
+        self.get(name)?
+            .ok_or_else(|| SecretError::Missing(name.to_string()))
+    }
+
+    /// Loads the current and, if present, the previous PASETO key.
+    /// Tokens encrypted with the previous key stay valid until `PASETO_PREVIOUS_KEY_VALID_UNTIL`.
+    pub fn paseto_keys(&self) -> Result<PasetoKeys, SecretError> {
+        let current = validate_paseto_key(PASETO_KEY, self.require(PASETO_KEY)?)?;
+        let previous = match self.get(PASETO_PREVIOUS_KEY)? {
+            Some(key) => {
+                let valid_until = self.require(PASETO_PREVIOUS_KEY_VALID_UNTIL)?;
+                let valid_until = DateTime::parse_from_rfc3339(valid_until.expose().trim())
+                    .map_err(|e| {
+                        SecretError::InvalidTimestamp(
+                            PASETO_PREVIOUS_KEY_VALID_UNTIL.to_string(),
+                            e,
+                        )
+                    })?;
+                Some((
+                    validate_paseto_key(PASETO_PREVIOUS_KEY, key)?,
+                    valid_until.with_timezone(&Utc),
+                ))
+            }
+            None => None,
+        };
+        Ok(PasetoKeys::new(current, previous))
+    }
+}
+
+fn read_secret_file(name: &str, path: &Path) -> Result<Secret, SecretError> {
+    std::fs::read_to_string(path)
+        // Files created with `echo` or an editor end with a newline
+        .map(|value| Secret(value.trim_end_matches(['\r', '\n']).to_string()))
+        .map_err(|e| SecretError::CannotReadFile(name.to_string(), path.to_path_buf(), e))
+}
+
+fn validate_paseto_key(name: &str, key: Secret) -> Result<Secret, SecretError> {
+    match key.expose().len() {
+        PASETO_KEY_LENGTH => Ok(key),
+        len => Err(SecretError::InvalidPasetoKey(name.to_string(), len)),
+    }
+}
@@ -0,0 +1,968 @@
+use crate::store::{search, Repository};
+use crate::types::account::{Account, AccountId, AccountProfile, Role, Session};
+use crate::types::answer::{Answer, AnswerId, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentId, CommentTarget, NewComment};
+use crate::types::moderation::ModerationStatus;
+use crate::types::pagination::{Cursor, Page};
+use crate::types::question::{NewQuestion, Question, QuestionFilter, QuestionId, QuestionSort};
+use crate::types::search::{SearchHit, SearchQuery};
+use crate::types::tag::{normalize_tags, TagCount, TagMatch};
+use crate::types::vote::Vote;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use handle_errors::Error;
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use tokio::sync::RwLock;
+
+/// A question in the format of `questions/questions.json`,
+/// where the ids are strings and the file maps them to the questions
+#[derive(Deserialize, Debug, Clone)]
+pub struct LegacyQuestion {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub tags: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default)]
+struct Data {
+    questions: BTreeMap<i32, Question>,
+    answers: BTreeMap<i32, Answer>,
+    accounts: BTreeMap<i32, Account>,
+    comments: BTreeMap<i32, Comment>,
+    /// When the questions were asked, searches filter by it
+    created_on: HashMap<i32, NaiveDateTime>,
+    /// When the answers were given, keyed by the id of the answer
+    answered_on: HashMap<i32, NaiveDateTime>,
+    /// Votes keyed by the ids of the question and of the account
+    question_votes: HashMap<(i32, i32), Vote>,
+    /// Votes keyed by the ids of the answer and of the account
+    answer_votes: HashMap<(i32, i32), Vote>,
+    next_question_id: i32,
+    next_answer_id: i32,
+    next_account_id: i32,
+    next_comment_id: i32,
+}
+
+impl Data {
+    fn next_id(counter: &mut i32) -> i32 {
+        *counter += 1;
+        *counter
+    }
+
+    fn visible_answers<'a>(&'a self, question: &'a Question) -> impl Iterator<Item = &'a Answer> {
+        self.answers
+            .values()
+            .filter(move |a| a.question_id == question.id && a.status.is_visible())
+    }
+
+    fn matches(&self, question: &Question, filter: &QuestionFilter) -> bool {
+        let created_on = self.created_on.get(&question.id.0);
+        has_tags(question, filter)
+            && filter
+                .author
+                .as_ref()
+                .is_none_or(|author| question.account_id.as_ref() == Some(author))
+            && filter
+                .created_after
+                .is_none_or(|after| created_on >= Some(&after))
+            && filter
+                .created_before
+                .is_none_or(|before| created_on < Some(&before))
+            && !(filter.unanswered && self.visible_answers(question).next().is_some())
+    }
+
+    /// The visible questions of a filter in its order, each with its cursor
+    fn list_questions(&self, filter: &QuestionFilter) -> Vec<(&Question, Cursor)> {
+        let mut questions: Vec<(&Question, Cursor)> = self
+            .questions
+            .values()
+            .filter(|q| q.status.is_visible() && self.matches(q, filter))
+            .map(|q| {
+                let key = self.sort_key(q, filter.sort);
+                (q, Cursor { key, id: q.id.0 })
+            })
+            .collect();
+        // The questions are in the order they were asked, which the stable sort keeps for ties
+        match filter.sort {
+            QuestionSort::Oldest => {}
+            QuestionSort::Newest => questions.reverse(),
+            _ => questions.sort_by_key(|(_, cursor)| Reverse(cursor.key)),
+        }
+        questions
+    }
+
+    /// Counts the visible comments on the question or answer again, after one of them changed
+    fn refresh_comment_count(&mut self, target: &CommentTarget) {
+        let count = self
+            .comments
+            .values()
+            .filter(|c| &c.target == target && c.status.is_visible())
+            .count() as i32;
+        match target {
+            CommentTarget::Question(id) => {
+                if let Some(question) = self.questions.get_mut(&id.0) {
+                    question.comment_count = count;
+                }
+            }
+            CommentTarget::Answer(id) => {
+                if let Some(answer) = self.answers.get_mut(&id.0) {
+                    answer.comment_count = count;
+                }
+            }
+        }
+    }
+
+    /// The id of the answer the question has accepted
+    fn accepted_answer(&self, question_id: i32) -> Option<i32> {
+        self.questions
+            .get(&question_id)
+            .and_then(|question| question.accepted_answer_id.as_ref())
+            .map(|id| id.0)
+    }
+
+    /// The visible answers to a question, the accepted one first and the others in the order
+    /// of the sort, each with its cursor
+    fn list_answers(&self, question_id: i32, sort: AnswerSort) -> Vec<(&Answer, Cursor)> {
+        let accepted = self.accepted_answer(question_id);
+        let mut answers: Vec<(&Answer, Cursor)> = self
+            .answers
+            .values()
+            .filter(|a| a.question_id.0 == question_id && a.status.is_visible())
+            .map(|a| {
+                let key = (sort == AnswerSort::MostVoted).then_some(a.score as i64);
+                (a, Cursor { key, id: a.id.0 })
+            })
+            .collect();
+        // The stable sort keeps the answers with the same score in the order they were given
+        answers.sort_by_key(|(_, cursor)| (Some(cursor.id) != accepted, Reverse(cursor.key)));
+        answers
+    }
+
+    /// What a question gets sorted by besides its id, times in whole seconds like the databases
+    fn sort_key(&self, question: &Question, sort: QuestionSort) -> Option<i64> {
+        match sort {
+            QuestionSort::Oldest | QuestionSort::Newest => None,
+            QuestionSort::MostAnswered => Some(self.visible_answers(question).count() as i64),
+            QuestionSort::RecentlyActive => self
+                .last_activity(question)
+                .map(|moment| moment.and_utc().timestamp()),
+            QuestionSort::MostVoted => Some(question.score as i64),
+        }
+    }
+
+    /// When the question was asked or got its latest visible answer
+    fn last_activity(&self, question: &Question) -> Option<NaiveDateTime> {
+        self.visible_answers(question)
+            .filter_map(|a| self.answered_on.get(&a.id.0))
+            .chain(self.created_on.get(&question.id.0))
+            .max()
+            .copied()
+    }
+}
+
+/// Keeps everything in memory, for demos and tests which should not need a database.
+/// Nothing survives a restart.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    data: RwLock<Data>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+
+    /// Seeds the store with the questions of a file in the `questions/questions.json` format
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = std::fs::read_to_string(path)?;
+        let questions: HashMap<String, LegacyQuestion> = serde_json::from_str(&file)?;
+        Ok(MemoryStore::from_legacy(questions.into_values())?)
+    }
+
+    /// Seeds the store with the questions, their tags get normalized like the ones of new questions
+    pub fn from_legacy<I: IntoIterator<Item = LegacyQuestion>>(
+        questions: I,
+    ) -> Result<Self, Error> {
+        let mut data = Data::default();
+        let mut without_id = Vec::new();
+
+        for question in questions {
+            match question.id.parse::<i32>() {
+                Ok(id) if id > 0 && !data.questions.contains_key(&id) => {
+                    data.next_question_id = data.next_question_id.max(id);
+                    data.questions.insert(id, legacy_to_question(id, question)?);
+                    data.created_on.insert(id, now());
+                }
+                _ => without_id.push(question),
+            }
+        }
+        // Questions with ids which are no numbers get the next free ones
+        for question in without_id {
+            let id = Data::next_id(&mut data.next_question_id);
+            data.questions.insert(id, legacy_to_question(id, question)?);
+            data.created_on.insert(id, now());
+        }
+
+        Ok(MemoryStore {
+            data: RwLock::new(data),
+        })
+    }
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+fn legacy_to_question(id: i32, question: LegacyQuestion) -> Result<Question, Error> {
+    Ok(Question {
+        id: QuestionId(id),
+        title: question.title,
+        content: question.content,
+        tags: normalize_tags(question.tags)?,
+        account_id: None,
+        status: ModerationStatus::Published,
+        score: 0,
+        accepted_answer_id: None,
+        comment_count: 0,
+    })
+}
+
+/// Whether an item comes after the cursor `after` in an order by the key, the highest first,
+/// and then by id. Without keys that is the order of the ids.
+fn comes_after(cursor: &Cursor, after: &Cursor) -> bool {
+    cursor.key < after.key || (cursor.key == after.key && cursor.id > after.id)
+}
+
+/// The sum of the votes of one question or answer
+fn score(votes: &HashMap<(i32, i32), Vote>, id: i32) -> i32 {
+    votes
+        .iter()
+        .filter(|((voted, _), _)| *voted == id)
+        .map(|(_, vote)| vote.value())
+        .sum()
+}
+
+fn has_tags(question: &Question, filter: &QuestionFilter) -> bool {
+    if filter.tags.is_empty() {
+        return true;
+    }
+    let tags = question.tags.as_deref().unwrap_or_default();
+    match filter.tag_match {
+        TagMatch::Any => filter.tags.iter().any(|tag| tags.contains(tag)),
+        TagMatch::All => filter.tags.iter().all(|tag| tags.contains(tag)),
+    }
+}
+
+/// Skips `offset` items and takes `limit` of the rest, like `LIMIT` and `OFFSET` in SQL
+fn page<'a, T: Clone + 'a>(
+    items: impl Iterator<Item = &'a T>,
+    limit: Option<i32>,
+    offset: i32,
+) -> Vec<T> {
+    let items = items.skip(offset.max(0) as usize);
+    match limit {
+        Some(limit) => items.take(limit.max(0) as usize).cloned().collect(),
+        None => items.cloned().collect(),
+    }
+}
+
+fn profile(account: &Account) -> AccountProfile {
+    AccountProfile {
+        id: account.id.clone().expect("stored accounts have an id"),
+        email: account.email.clone(),
+        role: account.role,
+        disabled: account.disabled,
+    }
+}
+
+#[async_trait]
+impl Repository for MemoryStore {
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Question>, Error> {
+        let data = self.data.read().await;
+        let questions = data.list_questions(filter);
+        Ok(page(questions.into_iter().map(|(q, _)| q), limit, offset))
+    }
+
+    async fn get_question_page(
+        &self,
+        filter: &QuestionFilter,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Question>, Error> {
+        let data = self.data.read().await;
+        let rows = data
+            .list_questions(filter)
+            .into_iter()
+            .filter(|(_, cursor)| {
+                after.is_none_or(|after| match filter.sort {
+                    QuestionSort::Newest => cursor.id < after.id,
+                    _ => comes_after(cursor, after),
+                })
+            })
+            .take(limit.max(0) as usize + 1)
+            .map(|(q, cursor)| (q.clone(), cursor))
+            .collect();
+        Ok(Page::new(rows, limit))
+    }
+
+    async fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, Error> {
+        let data = self.data.read().await;
+        let count = data
+            .questions
+            .values()
+            .filter(|q| q.status.is_visible() && data.matches(q, filter))
+            .count();
+        Ok(count as i64)
+    }
+
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        let data = self.data.read().await;
+        let mut counts: BTreeMap<&str, i64> = BTreeMap::new();
+        for question in data.questions.values().filter(|q| q.status.is_visible()) {
+            let tags: BTreeSet<&str> = question.tags.iter().flatten().map(String::as_str).collect();
+            for tag in tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+
+        let mut tags: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, questions)| TagCount {
+                tag: tag.to_string(),
+                questions,
+            })
+            .collect();
+        // The map is sorted by tag already, the stable sort keeps that for equal counts
+        tags.sort_by_key(|tag| Reverse(tag.questions));
+        Ok(tags)
+    }
+
+    async fn get_question(&self, question_id: i32) -> Result<Question, Error> {
+        self.data
+            .read()
+            .await
+            .questions
+            .get(&question_id)
+            .cloned()
+            .ok_or(Error::QuestionNotFound)
+    }
+
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Answer>, Error> {
+        let data = self.data.read().await;
+        let answers = data.list_answers(question_id, sort);
+        Ok(page(answers.into_iter().map(|(a, _)| a), limit, offset))
+    }
+
+    async fn get_answer_page(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Answer>, Error> {
+        let data = self.data.read().await;
+        let accepted = data.accepted_answer(question_id);
+        // Everything comes after the accepted answer, which comes after nothing
+        let rows = data
+            .list_answers(question_id, sort)
+            .into_iter()
+            .filter(|(_, cursor)| {
+                after.is_none_or(|after| {
+                    Some(cursor.id) != accepted
+                        && (Some(after.id) == accepted || comes_after(cursor, after))
+                })
+            })
+            .take(limit.max(0) as usize + 1)
+            .map(|(a, cursor)| (a.clone(), cursor))
+            .collect();
+        Ok(Page::new(rows, limit))
+    }
+
+    async fn count_answers(&self, question_id: i32) -> Result<i64, Error> {
+        let data = self.data.read().await;
+        let count = data
+            .answers
+            .values()
+            .filter(|a| a.question_id.0 == question_id && a.status.is_visible())
+            .count();
+        Ok(count as i64)
+    }
+
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Question, Error> {
+        let mut data = self.data.write().await;
+        if !data.questions.contains_key(&question_id) {
+            return Err(Error::QuestionNotFound);
+        }
+        let key = (question_id, account_id.0);
+        match vote {
+            Some(vote) => data.question_votes.insert(key, vote),
+            None => data.question_votes.remove(&key),
+        };
+
+        let score = score(&data.question_votes, question_id);
+        let question = data
+            .questions
+            .get_mut(&question_id)
+            .ok_or(Error::QuestionNotFound)?;
+        question.score = score;
+        Ok(question.clone())
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Answer, Error> {
+        let mut data = self.data.write().await;
+        if !data.answers.contains_key(&answer_id) {
+            return Err(Error::AnswerNotFound);
+        }
+        let key = (answer_id, account_id.0);
+        match vote {
+            Some(vote) => data.answer_votes.insert(key, vote),
+            None => data.answer_votes.remove(&key),
+        };
+
+        let score = score(&data.answer_votes, answer_id);
+        let answer = data
+            .answers
+            .get_mut(&answer_id)
+            .ok_or(Error::AnswerNotFound)?;
+        answer.score = score;
+        Ok(answer.clone())
+    }
+
+    async fn set_accepted_answer(
+        &self,
+        question_id: i32,
+        answer_id: Option<i32>,
+    ) -> Result<Question, Error> {
+        let mut data = self.data.write().await;
+        if !data.questions.contains_key(&question_id) {
+            return Err(Error::QuestionNotFound);
+        }
+        if let Some(answer_id) = answer_id {
+            match data.answers.get(&answer_id) {
+                Some(answer) if answer.question_id.0 == question_id => (),
+                _ => return Err(Error::AnswerNotFound),
+            }
+        }
+
+        let question = data
+            .questions
+            .get_mut(&question_id)
+            .ok_or(Error::QuestionNotFound)?;
+        question.accepted_answer_id = answer_id.map(AnswerId);
+        Ok(question.clone())
+    }
+
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<SearchHit>, Error> {
+        let terms = search::terms(&query.q);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (from, until) = search::bounds(query);
+
+        let data = self.data.read().await;
+        let hits = data
+            .questions
+            .values()
+            .filter(|q| q.status.is_visible())
+            .filter(|q| {
+                let tags = q.tags.as_deref().unwrap_or_default();
+                query.tags.iter().all(|tag| tags.contains(tag))
+            })
+            .filter(|q| {
+                let created_on = data.created_on.get(&q.id.0);
+                from.is_none_or(|from| created_on >= Some(&from))
+                    && until.is_none_or(|until| created_on < Some(&until))
+            })
+            .filter_map(|q| {
+                let answers: Vec<Answer> = data
+                    .answers
+                    .values()
+                    .filter(|a| a.question_id == q.id && a.status.is_visible())
+                    .cloned()
+                    .collect();
+                search::hit(q.clone(), &answers, &terms)
+            })
+            .collect();
+        Ok(search::page(hits, limit, offset))
+    }
+
+    async fn get_questions_after(&self, after_id: i32, limit: i32) -> Result<Vec<Question>, Error> {
+        Ok(self
+            .data
+            .read()
+            .await
+            .questions
+            .range(after_id.saturating_add(1)..)
+            .take(limit.max(0) as usize)
+            .map(|(_, question)| question.clone())
+            .collect())
+    }
+
+    async fn get_answers_of_questions(&self, question_ids: Vec<i32>) -> Result<Vec<Answer>, Error> {
+        Ok(self
+            .data
+            .read()
+            .await
+            .answers
+            .values()
+            .filter(|answer| question_ids.contains(&answer.question_id.0))
+            .cloned()
+            .collect())
+    }
+
+    async fn add_question(
+        &self,
+        question: NewQuestion,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Question, Error> {
+        let mut data = self.data.write().await;
+        let id = Data::next_id(&mut data.next_question_id);
+        let question = Question {
+            id: QuestionId(id),
+            title: question.title,
+            content: question.content,
+            tags: question.tags,
+            account_id: Some(account_id),
+            status,
+            score: 0,
+            accepted_answer_id: None,
+            comment_count: 0,
+        };
+        data.questions.insert(id, question.clone());
+        data.created_on.insert(id, now());
+        Ok(question)
+    }
+
+    async fn question_owner(&self, question_id: i32) -> Result<Option<AccountId>, Error> {
+        match self.data.read().await.questions.get(&question_id) {
+            Some(question) => Ok(question.account_id.clone()),
+            None => Err(Error::QuestionNotFound),
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        session: &Session,
+    ) -> Result<Question, Error> {
+        self.ensure_question_owner(question_id, session).await?;
+
+        let mut data = self.data.write().await;
+        let stored = data
+            .questions
+            .get_mut(&question_id)
+            .ok_or(Error::QuestionNotFound)?;
+        stored.title = question.title;
+        stored.content = question.content;
+        stored.tags = question.tags;
+        stored.status = stored.status.edited(question.status);
+        Ok(stored.clone())
+    }
+
+    async fn delete_question(&self, question_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_question_owner(question_id, session).await?;
+
+        let mut data = self.data.write().await;
+        data.questions.remove(&question_id);
+        data.created_on.remove(&question_id);
+        // Same as the foreign key in the database
+        data.answers
+            .retain(|_, answer| answer.question_id.0 != question_id);
+        data.question_votes
+            .retain(|(voted, _), _| *voted != question_id);
+        let Data {
+            answers,
+            answered_on,
+            answer_votes,
+            comments,
+            ..
+        } = &mut *data;
+        answered_on.retain(|id, _| answers.contains_key(id));
+        answer_votes.retain(|(voted, _), _| answers.contains_key(voted));
+        comments.retain(|_, comment| match &comment.target {
+            CommentTarget::Question(id) => id.0 != question_id,
+            CommentTarget::Answer(id) => answers.contains_key(&id.0),
+        });
+        Ok(true)
+    }
+
+    async fn add_answer(
+        &self,
+        answer: NewAnswer,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error> {
+        let mut data = self.data.write().await;
+        if !data.questions.contains_key(&answer.question_id.0) {
+            return Err(Error::QuestionNotFound);
+        }
+        let id = Data::next_id(&mut data.next_answer_id);
+        let answer = Answer {
+            id: AnswerId(id),
+            content: answer.content,
+            question_id: answer.question_id,
+            account_id: Some(account_id),
+            status,
+            score: 0,
+            comment_count: 0,
+        };
+        data.answers.insert(id, answer.clone());
+        data.answered_on.insert(id, now());
+        Ok(answer)
+    }
+
+    async fn get_answer(&self, answer_id: i32) -> Result<Answer, Error> {
+        self.data
+            .read()
+            .await
+            .answers
+            .get(&answer_id)
+            .cloned()
+            .ok_or(Error::AnswerNotFound)
+    }
+
+    async fn answer_owner(&self, answer_id: i32) -> Result<Option<AccountId>, Error> {
+        match self.data.read().await.answers.get(&answer_id) {
+            Some(answer) => Ok(answer.account_id.clone()),
+            None => Err(Error::AnswerNotFound),
+        }
+    }
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        session: &Session,
+    ) -> Result<Answer, Error> {
+        self.ensure_answer_owner(answer_id, session).await?;
+
+        let mut data = self.data.write().await;
+        let stored = data
+            .answers
+            .get_mut(&answer_id)
+            .ok_or(Error::AnswerNotFound)?;
+        stored.content = answer.content;
+        stored.status = stored.status.edited(answer.status);
+        Ok(stored.clone())
+    }
+
+    async fn delete_answer(&self, answer_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_answer_owner(answer_id, session).await?;
+
+        let mut data = self.data.write().await;
+        data.answers.remove(&answer_id);
+        data.answered_on.remove(&answer_id);
+        data.answer_votes
+            .retain(|(voted, _), _| *voted != answer_id);
+        data.comments
+            .retain(|_, comment| comment.target != CommentTarget::Answer(AnswerId(answer_id)));
+        // Same as the foreign key in the database
+        for question in data.questions.values_mut() {
+            if question
+                .accepted_answer_id
+                .as_ref()
+                .is_some_and(|id| id.0 == answer_id)
+            {
+                question.accepted_answer_id = None;
+            }
+        }
+        Ok(true)
+    }
+
+    async fn get_comments(
+        &self,
+        target: &CommentTarget,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Comment>, Error> {
+        let data = self.data.read().await;
+        let visible = data
+            .comments
+            .values()
+            .filter(|c| &c.target == target && c.status.is_visible());
+        Ok(page(visible, limit, offset))
+    }
+
+    async fn get_comment(&self, comment_id: i32) -> Result<Comment, Error> {
+        self.data
+            .read()
+            .await
+            .comments
+            .get(&comment_id)
+            .cloned()
+            .ok_or(Error::CommentNotFound)
+    }
+
+    async fn add_comment(
+        &self,
+        comment: NewComment,
+        target: CommentTarget,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error> {
+        let mut data = self.data.write().await;
+        let exists = match &target {
+            CommentTarget::Question(id) => data.questions.contains_key(&id.0),
+            CommentTarget::Answer(id) => data.answers.contains_key(&id.0),
+        };
+        if !exists {
+            return Err(target.not_found());
+        }
+        let id = Data::next_id(&mut data.next_comment_id);
+        let comment = Comment {
+            id: CommentId(id),
+            content: comment.content,
+            target,
+            account_id: Some(account_id),
+            status,
+        };
+        data.comments.insert(id, comment.clone());
+        data.refresh_comment_count(&comment.target);
+        Ok(comment)
+    }
+
+    async fn comment_owner(&self, comment_id: i32) -> Result<Option<AccountId>, Error> {
+        match self.data.read().await.comments.get(&comment_id) {
+            Some(comment) => Ok(comment.account_id.clone()),
+            None => Err(Error::CommentNotFound),
+        }
+    }
+
+    async fn update_comment(
+        &self,
+        comment: Comment,
+        comment_id: i32,
+        session: &Session,
+    ) -> Result<Comment, Error> {
+        self.ensure_comment_owner(comment_id, session).await?;
+
+        let mut data = self.data.write().await;
+        let stored = data
+            .comments
+            .get_mut(&comment_id)
+            .ok_or(Error::CommentNotFound)?;
+        stored.content = comment.content;
+        stored.status = stored.status.edited(comment.status);
+        let stored = stored.clone();
+        data.refresh_comment_count(&stored.target);
+        Ok(stored)
+    }
+
+    async fn delete_comment(&self, comment_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_comment_owner(comment_id, session).await?;
+
+        let mut data = self.data.write().await;
+        if let Some(comment) = data.comments.remove(&comment_id) {
+            data.refresh_comment_count(&comment.target);
+        }
+        Ok(true)
+    }
+
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        let mut data = self.data.write().await;
+        if data.accounts.values().any(|a| a.email == account.email) {
+            return Err(Error::AccountAlreadyExists);
+        }
+        let id = Data::next_id(&mut data.next_account_id);
+        data.accounts.insert(
+            id,
+            Account {
+                id: Some(AccountId(id)),
+                ..account
+            },
+        );
+        Ok(true)
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        self.data
+            .read()
+            .await
+            .accounts
+            .values()
+            .find(|account| account.email == email)
+            .cloned()
+            .ok_or(Error::AccountNotFound)
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<AccountProfile>, Error> {
+        Ok(self
+            .data
+            .read()
+            .await
+            .accounts
+            .values()
+            .map(profile)
+            .collect())
+    }
+
+    async fn update_account_role(
+        &self,
+        account_id: AccountId,
+        role: Role,
+    ) -> Result<AccountProfile, Error> {
+        let mut data = self.data.write().await;
+        let account = data
+            .accounts
+            .get_mut(&account_id.0)
+            .ok_or(Error::AccountNotFound)?;
+        account.role = role;
+        Ok(profile(account))
+    }
+
+    async fn get_account_profile(&self, account_id: AccountId) -> Result<AccountProfile, Error> {
+        self.data
+            .read()
+            .await
+            .accounts
+            .get(&account_id.0)
+            .map(profile)
+            .ok_or(Error::AccountNotFound)
+    }
+
+    async fn set_account_disabled(
+        &self,
+        account_id: AccountId,
+        disabled: bool,
+    ) -> Result<AccountProfile, Error> {
+        let mut data = self.data.write().await;
+        let account = data
+            .accounts
+            .get_mut(&account_id.0)
+            .ok_or(Error::AccountNotFound)?;
+        account.disabled = disabled;
+        Ok(profile(account))
+    }
+
+    async fn update_account_password(
+        &self,
+        account_id: AccountId,
+        password: String,
+    ) -> Result<AccountProfile, Error> {
+        let mut data = self.data.write().await;
+        let account = data
+            .accounts
+            .get_mut(&account_id.0)
+            .ok_or(Error::AccountNotFound)?;
+        account.password = password;
+        Ok(profile(account))
+    }
+
+    async fn get_questions_by_status(
+        &self,
+        status: ModerationStatus,
+    ) -> Result<Vec<Question>, Error> {
+        Ok(self
+            .data
+            .read()
+            .await
+            .questions
+            .values()
+            .filter(|question| question.status == status)
+            .cloned()
+            .collect())
+    }
+
+    async fn set_question_status(
+        &self,
+        question_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Question, Error> {
+        let mut data = self.data.write().await;
+        let question = data
+            .questions
+            .get_mut(&question_id)
+            .ok_or(Error::QuestionNotFound)?;
+        question.status = status;
+        Ok(question.clone())
+    }
+
+    async fn get_answers_by_status(&self, status: ModerationStatus) -> Result<Vec<Answer>, Error> {
+        Ok(self
+            .data
+            .read()
+            .await
+            .answers
+            .values()
+            .filter(|answer| answer.status == status)
+            .cloned()
+            .collect())
+    }
+
+    async fn set_answer_status(
+        &self,
+        answer_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error> {
+        let mut data = self.data.write().await;
+        let answer = data
+            .answers
+            .get_mut(&answer_id)
+            .ok_or(Error::AnswerNotFound)?;
+        answer.status = status;
+        Ok(answer.clone())
+    }
+
+    async fn get_comments_by_status(
+        &self,
+        status: ModerationStatus,
+    ) -> Result<Vec<Comment>, Error> {
+        Ok(self
+            .data
+            .read()
+            .await
+            .comments
+            .values()
+            .filter(|comment| comment.status == status)
+            .cloned()
+            .collect())
+    }
+
+    async fn set_comment_status(
+        &self,
+        comment_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error> {
+        let mut data = self.data.write().await;
+        let comment = data
+            .comments
+            .get_mut(&comment_id)
+            .ok_or(Error::CommentNotFound)?;
+        comment.status = status;
+        let comment = comment.clone();
+        data.refresh_comment_count(&comment.target);
+        Ok(comment)
+    }
+}
@@ -0,0 +1,270 @@
+use crate::config::{DatabaseConfig, MigrateAction};
+use crate::store::postgres::PostgresStore;
+use crate::store::sqlite::SqliteStore;
+use async_trait::async_trait;
+use sqlx::migrate::{Migrate, MigrateError, Migration, Migrator};
+use sqlx::{PgConnection, SqliteConnection};
+use std::collections::HashMap;
+
+pub static POSTGRES: Migrator = sqlx::migrate!("migrations/postgres");
+pub static SQLITE: Migrator = sqlx::migrate!("migrations/sqlite");
+
+/// Changes to the `_sqlx_migrations` table which sqlx itself does not offer
+#[async_trait]
+trait Repair: Migrate + Send {
+    /// Removes the record of a migration, as if it never ran
+    async fn forget(&mut self, version: i64) -> Result<(), sqlx::Error>;
+
+    async fn set_checksum(&mut self, version: i64, checksum: &[u8]) -> Result<(), sqlx::Error>;
+}
+
+#[async_trait]
+impl Repair for PgConnection {
+    async fn forget(&mut self, version: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = $1")
+            .bind(version)
+            .execute(self)
+            .await
+            .map(|_| ())
+    }
+
+    async fn set_checksum(&mut self, version: i64, checksum: &[u8]) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = $1 WHERE version = $2")
+            .bind(checksum)
+            .bind(version)
+            .execute(self)
+            .await
+            .map(|_| ())
+    }
+}
+
+#[async_trait]
+impl Repair for SqliteConnection {
+    async fn forget(&mut self, version: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM _sqlx_migrations WHERE version = ?")
+            .bind(version)
+            .execute(self)
+            .await
+            .map(|_| ())
+    }
+
+    async fn set_checksum(&mut self, version: i64, checksum: &[u8]) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = ? WHERE version = ?")
+            .bind(checksum)
+            .bind(version)
+            .execute(self)
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Runs the `migrate` subcommand against the store selected by `database.url`
+pub async fn run(
+    config: &DatabaseConfig,
+    action: MigrateAction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if config.url.starts_with("memory://") {
+        return Err("the memory:// store has no schema to migrate".into());
+    }
+
+    if config.url.starts_with("sqlite:") {
+        let store = SqliteStore::new(&config.url, 1).await?;
+        let mut connection = store.connection.acquire().await?;
+        return execute(&mut *connection, &SQLITE, action).await;
+    }
+
+    let store = PostgresStore::new(&config.url, 1).await?;
+    let mut connection = store.connection.acquire().await?;
+    execute(&mut *connection, &POSTGRES, action).await
+}
+
+async fn execute<C: Repair>(
+    connection: &mut C,
+    migrator: &Migrator,
+    action: MigrateAction,
+) -> Result<(), Box<dyn std::error::Error>> {
+    connection.lock().await?;
+    connection.ensure_migrations_table().await?;
+
+    let res = match action {
+        MigrateAction::Up => up(connection, migrator).await,
+        MigrateAction::Down { steps } => down(connection, migrator, steps).await,
+        MigrateAction::Status => status(connection, migrator).await,
+        MigrateAction::Repair => repair(connection, migrator).await,
+    };
+
+    connection.unlock().await?;
+    res
+}
+
+fn up_migrations(migrator: &Migrator) -> impl Iterator<Item = &Migration> {
+    migrator
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+}
+
+/// Applied versions and their checksums, except for a migration which failed
+async fn applied<C: Repair>(connection: &mut C) -> Result<HashMap<i64, Vec<u8>>, MigrateError> {
+    let dirty = connection.dirty_version().await?;
+    Ok(connection
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .filter(|m| Some(m.version) != dirty)
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect())
+}
+
+async fn up<C: Repair>(
+    connection: &mut C,
+    migrator: &Migrator,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(version) = connection.dirty_version().await? {
+        return Err(format!(
+            "migration {} failed before, run `migrate repair` first",
+            version
+        )
+        .into());
+    }
+    let applied = applied(connection).await?;
+    for version in applied.keys() {
+        if !migrator.version_exists(*version) {
+            return Err(format!(
+                "migration {} is applied but unknown to this build, run `migrate repair` first",
+                version
+            )
+            .into());
+        }
+    }
+
+    let mut count = 0;
+    for migration in up_migrations(migrator) {
+        match applied.get(&migration.version) {
+            Some(checksum) if checksum.as_slice() != &*migration.checksum => {
+                return Err(format!(
+                    "migration {} changed since it was applied, run `migrate repair` first",
+                    migration.version
+                )
+                .into());
+            }
+            Some(_) => {}
+            None => {
+                let elapsed = connection.apply(migration).await?;
+                println!(
+                    "Applied {} {} ({} ms)",
+                    migration.version,
+                    migration.description,
+                    elapsed.as_millis()
+                );
+                count += 1;
+            }
+        }
+    }
+    if count == 0 {
+        println!("Nothing to apply, the schema is up to date");
+    }
+    Ok(())
+}
+
+async fn down<C: Repair>(
+    connection: &mut C,
+    migrator: &Migrator,
+    steps: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut versions: Vec<i64> = applied(connection).await?.into_keys().collect();
+    versions.sort_unstable_by(|a, b| b.cmp(a));
+    if versions.is_empty() {
+        println!("Nothing to revert, no migration is applied");
+    }
+
+    for version in versions.into_iter().take(steps) {
+        let migration = migrator
+            .iter()
+            .find(|m| m.version == version && m.migration_type.is_down_migration())
+            .ok_or_else(|| format!("migration {} cannot be reverted by this build", version))?;
+        let elapsed = connection.revert(migration).await?;
+        println!(
+            "Reverted {} {} ({} ms)",
+            migration.version,
+            migration.description,
+            elapsed.as_millis()
+        );
+    }
+    Ok(())
+}
+
+async fn status<C: Repair>(
+    connection: &mut C,
+    migrator: &Migrator,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dirty = connection.dirty_version().await?;
+    let applied = applied(connection).await?;
+
+    for migration in up_migrations(migrator) {
+        let state = match applied.get(&migration.version) {
+            _ if dirty == Some(migration.version) => "failed",
+            Some(checksum) if checksum.as_slice() != &*migration.checksum => "changed",
+            Some(_) => "applied",
+            None => "pending",
+        };
+        println!(
+            "{:<16}{:<10}{}",
+            migration.version, state, migration.description
+        );
+    }
+
+    let mut unknown: Vec<&i64> = applied
+        .keys()
+        .filter(|version| !migrator.version_exists(**version))
+        .collect();
+    unknown.sort();
+    for version in unknown {
+        println!("{:<16}{:<10}(not part of this build)", version, "unknown");
+    }
+    Ok(())
+}
+
+async fn repair<C: Repair>(
+    connection: &mut C,
+    migrator: &Migrator,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut changes = 0;
+
+    // Postgres and SQLite roll back a failed migration, so it can simply run again
+    if let Some(version) = connection.dirty_version().await? {
+        connection.forget(version).await?;
+        println!(
+            "Forgot failed migration {}, it runs again on `migrate up`",
+            version
+        );
+        changes += 1;
+    }
+
+    let mut applied: Vec<(i64, Vec<u8>)> = applied(connection).await?.into_iter().collect();
+    applied.sort();
+    for (version, checksum) in applied {
+        match up_migrations(migrator).find(|m| m.version == version) {
+            None => {
+                connection.forget(version).await?;
+                println!("Forgot migration {}, it is not part of this build", version);
+                changes += 1;
+            }
+            Some(migration) if checksum.as_slice() != &*migration.checksum => {
+                connection
+                    .set_checksum(version, &migration.checksum)
+                    .await?;
+                println!(
+                    "Accepted the changed migration {} {}",
+                    version, migration.description
+                );
+                changes += 1;
+            }
+            Some(_) => {}
+        }
+    }
+
+    if changes == 0 {
+        println!("Nothing to repair");
+    }
+    Ok(())
+}
@@ -0,0 +1,331 @@
+use crate::config::DatabaseConfig;
+use crate::types::account::{Account, AccountId, AccountProfile, Role, Session};
+use crate::types::answer::{Answer, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentTarget, NewComment};
+use crate::types::moderation::ModerationStatus;
+use crate::types::pagination::{Cursor, Page};
+use crate::types::question::{NewQuestion, Question, QuestionFilter};
+use crate::types::search::{SearchHit, SearchQuery};
+use crate::types::tag::TagCount;
+use crate::types::vote::Vote;
+use async_trait::async_trait;
+use handle_errors::Error;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+pub mod memory;
+pub mod migrations;
+pub mod postgres;
+mod search;
+pub mod sqlite;
+
+#[cfg(test)]
+mod tests;
+
+use memory::MemoryStore;
+use postgres::PostgresStore;
+use sqlite::SqliteStore;
+
+/// Everything the routes need to read and write questions, answers and accounts
+#[async_trait]
+pub trait Repository: Debug + Send + Sync {
+    /// Visible questions which match the filter, in the order it asks for
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Question>, Error>;
+
+    /// A page of the questions `get_questions` lists, starting after the cursor,
+    /// the cursor has to come from the same order
+    async fn get_question_page(
+        &self,
+        filter: &QuestionFilter,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Question>, Error>;
+
+    /// The number of questions `get_questions` lists without a limit
+    async fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, Error>;
+
+    /// The tags of the visible questions with the number of questions, the most used first
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error>;
+
+    /// A question whatever its status, fails with `QuestionNotFound` if there is none with the id
+    async fn get_question(&self, question_id: i32) -> Result<Question, Error>;
+
+    /// The visible answers to a question, the accepted one first and the others in the order
+    /// the sort asks for
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Answer>, Error>;
+
+    /// A page of the visible answers to a question, starting after the cursor,
+    /// the cursor has to come from the same order
+    async fn get_answer_page(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Answer>, Error>;
+
+    /// The number of visible answers to a question
+    async fn count_answers(&self, question_id: i32) -> Result<i64, Error>;
+
+    /// Sets the vote of the account on a question, `None` takes it back.
+    /// Returns the question with its new score, fails with `QuestionNotFound`
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Question, Error>;
+
+    /// Sets the vote of the account on an answer, `None` takes it back.
+    /// Returns the answer with its new score, fails with `AnswerNotFound`
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Answer, Error>;
+
+    /// Marks an answer to the question as accepted, `None` takes the acceptance back.
+    /// Fails with `QuestionNotFound`, or with `AnswerNotFound` unless the answer is one to the question
+    async fn set_accepted_answer(
+        &self,
+        question_id: i32,
+        answer_id: Option<i32>,
+    ) -> Result<Question, Error>;
+
+    /// Visible questions which match the search in their title, content or visible answers,
+    /// the best matches first
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<SearchHit>, Error>;
+
+    /// Questions of every status with an id above `after_id`, ordered by id,
+    /// so exports can page through all of them
+    async fn get_questions_after(&self, after_id: i32, limit: i32) -> Result<Vec<Question>, Error>;
+
+    /// The answers of every status to the given questions, ordered by id
+    async fn get_answers_of_questions(&self, question_ids: Vec<i32>) -> Result<Vec<Answer>, Error>;
+
+    async fn add_question(
+        &self,
+        question: NewQuestion,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Question, Error>;
+
+    /// Looks up the owner of a question, fails with `QuestionNotFound`
+    /// if there is no question with the given id
+    async fn question_owner(&self, question_id: i32) -> Result<Option<AccountId>, Error>;
+
+    /// Refuses with `Forbidden` unless the account created the question,
+    /// moderators and admins may change every question
+    async fn ensure_question_owner(
+        &self,
+        question_id: i32,
+        session: &Session,
+    ) -> Result<(), Error> {
+        match self.question_owner(question_id).await? {
+            _ if session.role >= Role::Moderator => Ok(()),
+            Some(owner) if owner == session.account_id => Ok(()),
+            _ => Err(Error::Forbidden),
+        }
+    }
+
+    /// Changes a question, pending and rejected questions keep their status
+    /// whatever the status of the new text is, see [`ModerationStatus::edited`]
+    async fn update_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        session: &Session,
+    ) -> Result<Question, Error>;
+
+    async fn delete_question(&self, question_id: i32, session: &Session) -> Result<bool, Error>;
+
+    /// Fails with `QuestionNotFound` if the question of the answer does not exist
+    async fn add_answer(
+        &self,
+        answer: NewAnswer,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error>;
+
+    /// An answer whatever its status, fails with `AnswerNotFound` if there is none with the id
+    async fn get_answer(&self, answer_id: i32) -> Result<Answer, Error>;
+
+    /// Looks up the owner of an answer, fails with `AnswerNotFound`
+    /// if there is no answer with the given id
+    async fn answer_owner(&self, answer_id: i32) -> Result<Option<AccountId>, Error>;
+
+    /// Same as `ensure_question_owner`, for answers
+    async fn ensure_answer_owner(&self, answer_id: i32, session: &Session) -> Result<(), Error> {
+        match self.answer_owner(answer_id).await? {
+            _ if session.role >= Role::Moderator => Ok(()),
+            Some(owner) if owner == session.account_id => Ok(()),
+            _ => Err(Error::Forbidden),
+        }
+    }
+
+    /// Changes the content and the status of an answer, it stays with its question.
+    /// Pending and rejected answers keep their status, like questions do
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        session: &Session,
+    ) -> Result<Answer, Error>;
+
+    async fn delete_answer(&self, answer_id: i32, session: &Session) -> Result<bool, Error>;
+
+    /// The visible comments on a question or answer, in the order they were written
+    async fn get_comments(
+        &self,
+        target: &CommentTarget,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Comment>, Error>;
+
+    /// A comment whatever its status, fails with `CommentNotFound` if there is none with the id
+    async fn get_comment(&self, comment_id: i32) -> Result<Comment, Error>;
+
+    /// Fails with `QuestionNotFound` or `AnswerNotFound` if the target does not exist
+    async fn add_comment(
+        &self,
+        comment: NewComment,
+        target: CommentTarget,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error>;
+
+    /// Looks up the owner of a comment, fails with `CommentNotFound`
+    /// if there is no comment with the given id
+    async fn comment_owner(&self, comment_id: i32) -> Result<Option<AccountId>, Error>;
+
+    /// Same as `ensure_question_owner`, for comments
+    async fn ensure_comment_owner(&self, comment_id: i32, session: &Session) -> Result<(), Error> {
+        match self.comment_owner(comment_id).await? {
+            _ if session.role >= Role::Moderator => Ok(()),
+            Some(owner) if owner == session.account_id => Ok(()),
+            _ => Err(Error::Forbidden),
+        }
+    }
+
+    /// Changes the content and the status of a comment, it stays on its question or answer.
+    /// Pending and rejected comments keep their status, like questions do
+    async fn update_comment(
+        &self,
+        comment: Comment,
+        comment_id: i32,
+        session: &Session,
+    ) -> Result<Comment, Error>;
+
+    async fn delete_comment(&self, comment_id: i32, session: &Session) -> Result<bool, Error>;
+
+    async fn add_account(&self, account: Account) -> Result<bool, Error>;
+
+    async fn get_account(&self, email: String) -> Result<Account, Error>;
+
+    async fn get_accounts(&self) -> Result<Vec<AccountProfile>, Error>;
+
+    /// Fails with `AccountNotFound` if there is no account with the id
+    async fn get_account_profile(&self, account_id: AccountId) -> Result<AccountProfile, Error>;
+
+    async fn update_account_role(
+        &self,
+        account_id: AccountId,
+        role: Role,
+    ) -> Result<AccountProfile, Error>;
+
+    async fn set_account_disabled(
+        &self,
+        account_id: AccountId,
+        disabled: bool,
+    ) -> Result<AccountProfile, Error>;
+
+    /// Replaces the password hash of an account
+    async fn update_account_password(
+        &self,
+        account_id: AccountId,
+        password: String,
+    ) -> Result<AccountProfile, Error>;
+
+    async fn get_questions_by_status(
+        &self,
+        status: ModerationStatus,
+    ) -> Result<Vec<Question>, Error>;
+
+    async fn set_question_status(
+        &self,
+        question_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Question, Error>;
+
+    async fn get_answers_by_status(&self, status: ModerationStatus) -> Result<Vec<Answer>, Error>;
+
+    async fn set_answer_status(
+        &self,
+        answer_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error>;
+
+    async fn get_comments_by_status(&self, status: ModerationStatus)
+        -> Result<Vec<Comment>, Error>;
+
+    async fn set_comment_status(
+        &self,
+        comment_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error>;
+}
+
+/// The repository shared by all routes
+pub type Store = Arc<dyn Repository>;
+
+/// Opens the store selected by the scheme of `database.url`:
+/// `postgres://` or `sqlite:` (the migrations get applied), or `memory://`
+pub async fn connect(config: &DatabaseConfig) -> Result<Store, Box<dyn std::error::Error>> {
+    if config.url.starts_with("memory://") {
+        let store = match &config.seed_file {
+            Some(path) => MemoryStore::from_file(path)?,
+            None => MemoryStore::new(),
+        };
+        return Ok(Arc::new(store));
+    }
+
+    if config.url.starts_with("sqlite:") {
+        let store = SqliteStore::new(&config.url, config.max_connections).await?;
+        migrations::SQLITE
+            .run(&store.connection)
+            .await
+            .map_err(migration_error)?;
+        return Ok(Arc::new(store));
+    }
+
+    let store = PostgresStore::new(&config.url, config.max_connections).await?;
+    migrations::POSTGRES
+        .run(&store.connection)
+        .await
+        .map_err(migration_error)?;
+    Ok(Arc::new(store))
+}
+
+fn migration_error(error: sqlx::migrate::MigrateError) -> String {
+    format!(
+        "{} (see the `migrate status` and `migrate repair` commands)",
+        error
+    )
+}
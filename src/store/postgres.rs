@@ -0,0 +1,1285 @@
+use crate::store::{search, Repository};
+use crate::types::account::{Account, AccountId, AccountProfile, Role, Session};
+use crate::types::answer::{Answer, AnswerId, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentId, CommentTarget, NewComment};
+use crate::types::moderation::ModerationStatus;
+use crate::types::pagination::{Cursor, Page};
+use crate::types::question::{NewQuestion, Question, QuestionFilter, QuestionId, QuestionSort};
+use crate::types::search::{SearchHit, SearchQuery};
+use crate::types::tag::{TagCount, TagMatch};
+use crate::types::vote::Vote;
+use async_trait::async_trait;
+use handle_errors::Error;
+use sqlx::postgres::{PgArguments, PgConnection, PgPool, PgPoolOptions, PgRow};
+use sqlx::query::Query;
+use sqlx::{Postgres, Row};
+
+fn map_question(row: PgRow) -> Question {
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: row.get("tags"),
+        account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+        status: row.get::<String, _>("status").parse().unwrap_or_default(),
+        score: row.get("score"),
+        accepted_answer_id: row
+            .get::<Option<i32>, _>("accepted_answer_id")
+            .map(AnswerId),
+        comment_count: row.get("comment_count"),
+    }
+}
+
+fn map_answer(row: PgRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("question_id")),
+        account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+        status: row.get::<String, _>("status").parse().unwrap_or_default(),
+        score: row.get("score"),
+        comment_count: row.get("comment_count"),
+    }
+}
+
+fn map_comment(row: PgRow) -> Comment {
+    let target = match row.get::<Option<i32>, _>("question_id") {
+        Some(id) => CommentTarget::Question(QuestionId(id)),
+        None => CommentTarget::Answer(AnswerId(row.get("answer_id"))),
+    };
+    Comment {
+        id: CommentId(row.get("id")),
+        content: row.get("content"),
+        target,
+        account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+        status: row.get::<String, _>("status").parse().unwrap_or_default(),
+    }
+}
+
+fn map_profile(row: PgRow) -> AccountProfile {
+    AccountProfile {
+        id: AccountId(row.get("id")),
+        email: row.get("email"),
+        role: row.get::<String, _>("role").parse().unwrap_or_default(),
+        disabled: row.get("disabled"),
+    }
+}
+
+fn query_error(error: sqlx::Error) -> Error {
+    tracing::event!(tracing::Level::ERROR, "{:?}", error);
+    Error::DatabaseQueryError(error)
+}
+
+/// Finds the questions whose own text or one of whose visible answers matches the search.
+/// The rank of the best answer adds a little to the rank of the question.
+const SEARCH: &str = "SELECT questions.id, questions.title, questions.content, questions.tags,
+        questions.account_id, questions.status, questions.score, questions.accepted_answer_id,
+        questions.comment_count,
+        (CASE WHEN questions.search @@ query THEN ts_rank(questions.search, query) ELSE 0 END
+            + 0.1 * coalesce(best.rank, 0))::real AS rank,
+        ts_headline(
+            'english',
+            translate(
+                CASE WHEN questions.search @@ query
+                    THEN questions.title || ' ' || questions.content
+                    ELSE best.content
+                END,
+                chr(2) || chr(3), ''),
+            query,
+            'MaxWords=20, MinWords=5, StartSel=' || chr(2) || ', StopSel=' || chr(3)
+        ) AS snippet
+    FROM questions
+    CROSS JOIN websearch_to_tsquery('english', $1) AS query
+    LEFT JOIN LATERAL (
+        SELECT answers.content, ts_rank(answers.search, query) AS rank
+        FROM answers
+        WHERE answers.question_id = questions.id
+            AND answers.status IN ($2, $3)
+            AND answers.search @@ query
+        ORDER BY rank DESC, answers.id
+        LIMIT 1
+    ) AS best ON true
+    WHERE questions.status IN ($2, $3)
+        AND (questions.search @@ query OR best.content IS NOT NULL)
+        AND ($4::text[] IS NULL OR questions.tags @> $4)
+        AND ($5::timestamp IS NULL OR questions.created_on >= $5::timestamp)
+        AND ($6::timestamp IS NULL OR questions.created_on < $6::timestamp)
+    ORDER BY rank DESC, questions.id
+    LIMIT $7 OFFSET $8";
+
+/// The visible questions of a filter with the value they get sorted by as `sort_key`,
+/// `bind_filter` binds `$1` to `$7`
+fn filtered_questions(filter: &QuestionFilter) -> String {
+    // Both operators can use the GIN index on the tags
+    let operator = match filter.tag_match {
+        TagMatch::Any => "&&",
+        TagMatch::All => "@>",
+    };
+    let sort_key = match filter.sort {
+        QuestionSort::Oldest | QuestionSort::Newest => "NULL::bigint",
+        QuestionSort::MostAnswered => {
+            "(SELECT count(*) FROM answers
+              WHERE answers.question_id = questions.id AND answers.status IN ($1, $2))"
+        }
+        // In whole seconds, so the key fits into a cursor
+        QuestionSort::RecentlyActive => {
+            "floor(extract(epoch FROM GREATEST(created_on, (SELECT max(answers.created_on)
+              FROM answers
+              WHERE answers.question_id = questions.id AND answers.status IN ($1, $2)))))::bigint"
+        }
+        QuestionSort::MostVoted => "score::bigint",
+    };
+
+    format!(
+        "SELECT *, {} AS sort_key FROM questions
+         WHERE status IN ($1, $2) AND ($3::text[] IS NULL OR tags {} $3)
+            AND ($4::integer IS NULL OR account_id = $4)
+            AND ($5::timestamp IS NULL OR created_on >= $5::timestamp)
+            AND ($6::timestamp IS NULL OR created_on < $6::timestamp)
+            AND (NOT $7 OR NOT EXISTS (SELECT 1 FROM answers
+                WHERE answers.question_id = questions.id AND answers.status IN ($1, $2)))",
+        sort_key, operator
+    )
+}
+
+fn bind_filter<'q>(
+    query: Query<'q, Postgres, PgArguments>,
+    filter: &QuestionFilter,
+) -> Query<'q, Postgres, PgArguments> {
+    query
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .bind((!filter.tags.is_empty()).then(|| filter.tags.clone()))
+        .bind(filter.author.as_ref().map(|author| author.0))
+        .bind(filter.created_after.map(search::timestamp))
+        .bind(filter.created_before.map(search::timestamp))
+        .bind(filter.unanswered)
+}
+
+/// The answer the question `$1` accepted
+const ACCEPTED: &str = "(SELECT accepted_answer_id FROM questions WHERE questions.id = $1)";
+
+/// The `ORDER BY` of the answers to the question `$1`, the accepted answer first
+fn answer_order(sort: AnswerSort) -> String {
+    let order = match sort {
+        AnswerSort::Oldest => "id",
+        AnswerSort::MostVoted => "score DESC, id",
+    };
+    format!("CASE WHEN id = {} THEN 0 ELSE 1 END, {}", ACCEPTED, order)
+}
+
+#[derive(Debug, Clone)]
+pub struct PostgresStore {
+    pub connection: PgPool,
+}
+
+impl PostgresStore {
+    /// The questions of a filter in its order with their cursors, after the cursor if there is one
+    async fn select_questions(
+        &self,
+        filter: &QuestionFilter,
+        after: Option<&Cursor>,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<(Question, Cursor)>, Error> {
+        let (follows, order) = match filter.sort {
+            QuestionSort::Oldest => ("id > $8", "id"),
+            QuestionSort::Newest => ("id < $8", "id DESC"),
+            _ => (
+                "(sort_key < $9 OR (sort_key = $9 AND id > $8))",
+                "sort_key DESC, id",
+            ),
+        };
+        let sql = format!(
+            "SELECT * FROM ({}) AS filtered
+             WHERE ($8::integer IS NULL OR {})
+             ORDER BY {} LIMIT $10 OFFSET $11",
+            filtered_questions(filter),
+            follows,
+            order
+        );
+
+        match bind_filter(sqlx::query(&sql), filter)
+            .bind(after.map(|after| after.id))
+            .bind(after.and_then(|after| after.key))
+            .bind(limit)
+            .bind(offset)
+            .map(|row: PgRow| {
+                let cursor = Cursor {
+                    key: row.get("sort_key"),
+                    id: row.get("id"),
+                };
+                (map_question(row), cursor)
+            })
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(rows) => Ok(rows),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    /// Counts the visible comments on the question or answer again, after one of them changed
+    async fn refresh_comment_count(
+        connection: &mut PgConnection,
+        target: &CommentTarget,
+    ) -> Result<(), sqlx::Error> {
+        let sql = match target {
+            CommentTarget::Question(_) => {
+                "UPDATE questions
+                    SET comment_count = (SELECT count(*) FROM comments
+                        WHERE question_id = $1 AND status IN ($2, $3))
+                    WHERE id = $1"
+            }
+            CommentTarget::Answer(_) => {
+                "UPDATE answers
+                    SET comment_count = (SELECT count(*) FROM comments
+                        WHERE answer_id = $1 AND status IN ($2, $3))
+                    WHERE id = $1"
+            }
+        };
+        sqlx::query(sql)
+            .bind(target.id())
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .execute(connection)
+            .await
+            .map(|_| ())
+    }
+
+    pub async fn new(db_url: &str, max_connections: u32) -> Result<Self, sqlx::Error> {
+        let db_pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .connect(db_url)
+            .await?;
+        Ok(PostgresStore {
+            connection: db_pool,
+        })
+    }
+}
+
+#[async_trait]
+impl Repository for PostgresStore {
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Question>, Error> {
+        let rows = self.select_questions(filter, None, limit, offset).await?;
+        Ok(rows.into_iter().map(|(question, _)| question).collect())
+    }
+
+    async fn get_question_page(
+        &self,
+        filter: &QuestionFilter,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Question>, Error> {
+        let rows = self
+            .select_questions(filter, after, Some(limit.saturating_add(1)), 0)
+            .await?;
+        Ok(Page::new(rows, limit))
+    }
+
+    async fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, Error> {
+        // Counting needs no sort values
+        let filter = QuestionFilter {
+            sort: QuestionSort::Oldest,
+            ..filter.clone()
+        };
+        let sql = format!(
+            "SELECT count(*) FROM ({}) AS filtered",
+            filtered_questions(&filter)
+        );
+        match bind_filter(sqlx::query(&sql), &filter)
+            .map(|row: PgRow| row.get(0))
+            .fetch_one(&self.connection)
+            .await
+        {
+            Ok(count) => Ok(count),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        match sqlx::query(
+            "SELECT tag, count(DISTINCT id) AS questions
+             FROM questions CROSS JOIN unnest(tags) AS tag
+             WHERE status IN ($1, $2)
+             GROUP BY tag
+             ORDER BY questions DESC, tag",
+        )
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .map(|row: PgRow| TagCount {
+            tag: row.get("tag"),
+            questions: row.get("questions"),
+        })
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(tags) => Ok(tags),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_question(&self, question_id: i32) -> Result<Question, Error> {
+        match sqlx::query("SELECT * from questions WHERE id = $1")
+            .bind(question_id)
+            .map(map_question)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(question)) => Ok(question),
+            Ok(None) => Err(Error::QuestionNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Answer>, Error> {
+        let sql = format!(
+            "SELECT * from answers WHERE question_id = $1 AND status IN ($2, $3)
+             ORDER BY {} LIMIT $4 OFFSET $5",
+            answer_order(sort)
+        );
+        match sqlx::query(&sql)
+            .bind(question_id)
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .bind(limit)
+            .bind(offset)
+            .map(map_answer)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_answer_page(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Answer>, Error> {
+        let follows = match sort {
+            AnswerSort::Oldest => "id > $4",
+            AnswerSort::MostVoted => "(score < $5 OR (score = $5 AND id > $4))",
+        };
+        // Every other answer comes after the accepted one, which comes after none
+        let sql = format!(
+            "SELECT * from answers
+             WHERE question_id = $1 AND status IN ($2, $3)
+                AND ($4::integer IS NULL OR (id IS DISTINCT FROM {accepted}
+                    AND ($4 = {accepted} OR {follows})))
+             ORDER BY {order} LIMIT $6",
+            accepted = ACCEPTED,
+            follows = follows,
+            order = answer_order(sort)
+        );
+        match sqlx::query(&sql)
+            .bind(question_id)
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .bind(after.map(|after| after.id))
+            .bind(after.and_then(|after| after.key))
+            .bind(limit.saturating_add(1))
+            .map(move |row: PgRow| {
+                let cursor = Cursor {
+                    key: (sort == AnswerSort::MostVoted)
+                        .then(|| i64::from(row.get::<i32, _>("score"))),
+                    id: row.get("id"),
+                };
+                (map_answer(row), cursor)
+            })
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(rows) => Ok(Page::new(rows, limit)),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn count_answers(&self, question_id: i32) -> Result<i64, Error> {
+        match sqlx::query(
+            "SELECT count(*) FROM answers WHERE question_id = $1 AND status IN ($2, $3)",
+        )
+        .bind(question_id)
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .map(|row: PgRow| row.get(0))
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(count) => Ok(count),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Question, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        // Locking the question keeps concurrent votes from summing up stale scores
+        sqlx::query("SELECT id FROM questions WHERE id = $1 FOR UPDATE")
+            .bind(question_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::QuestionNotFound)?;
+
+        let change = match vote {
+            Some(vote) => sqlx::query(
+                "INSERT INTO question_votes (question_id, account_id, value) VALUES ($1, $2, $3)
+                 ON CONFLICT (question_id, account_id) DO UPDATE SET value = excluded.value",
+            )
+            .bind(question_id)
+            .bind(account_id.0)
+            .bind(vote.value() as i16),
+            None => {
+                sqlx::query("DELETE FROM question_votes WHERE question_id = $1 AND account_id = $2")
+                    .bind(question_id)
+                    .bind(account_id.0)
+            }
+        };
+        change.execute(&mut *tx).await.map_err(query_error)?;
+
+        let question = sqlx::query(
+            "UPDATE questions
+                SET score = (SELECT coalesce(sum(value), 0) FROM question_votes WHERE question_id = $1)
+                WHERE id = $1
+                RETURNING id, title, content, tags, account_id, status, score, accepted_answer_id,
+                    comment_count",
+        )
+        .bind(question_id)
+        .map(map_question)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(question)
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Answer, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        sqlx::query("SELECT id FROM answers WHERE id = $1 FOR UPDATE")
+            .bind(answer_id)
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::AnswerNotFound)?;
+
+        let change = match vote {
+            Some(vote) => sqlx::query(
+                "INSERT INTO answer_votes (answer_id, account_id, value) VALUES ($1, $2, $3)
+                 ON CONFLICT (answer_id, account_id) DO UPDATE SET value = excluded.value",
+            )
+            .bind(answer_id)
+            .bind(account_id.0)
+            .bind(vote.value() as i16),
+            None => {
+                sqlx::query("DELETE FROM answer_votes WHERE answer_id = $1 AND account_id = $2")
+                    .bind(answer_id)
+                    .bind(account_id.0)
+            }
+        };
+        change.execute(&mut *tx).await.map_err(query_error)?;
+
+        let answer = sqlx::query(
+            "UPDATE answers
+                SET score = (SELECT coalesce(sum(value), 0) FROM answer_votes WHERE answer_id = $1)
+                WHERE id = $1
+                RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(answer_id)
+        .map(map_answer)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(answer)
+    }
+
+    async fn set_accepted_answer(
+        &self,
+        question_id: i32,
+        answer_id: Option<i32>,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions
+                SET accepted_answer_id = $2
+                WHERE id = $1 AND ($2::integer IS NULL
+                    OR EXISTS (SELECT 1 FROM answers WHERE id = $2 AND question_id = $1))
+                RETURNING id, title, content, tags, account_id, status, score, accepted_answer_id,
+                    comment_count",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .map(map_question)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(question)) => Ok(question),
+            // Either the question or the answer is missing
+            Ok(None) => {
+                self.get_question(question_id).await?;
+                Err(Error::AnswerNotFound)
+            }
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<SearchHit>, Error> {
+        let (from, until) = search::bounds(query);
+        let tags = (!query.tags.is_empty()).then(|| query.tags.clone());
+
+        match sqlx::query(SEARCH)
+            .bind(&query.q)
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .bind(tags)
+            .bind(from.map(search::timestamp))
+            .bind(until.map(search::timestamp))
+            .bind(limit)
+            .bind(offset)
+            .map(|row: PgRow| SearchHit {
+                rank: row.get("rank"),
+                snippet: search::headline(row.get("snippet")),
+                question: map_question(row),
+            })
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(hits) => Ok(hits),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_questions_after(&self, after_id: i32, limit: i32) -> Result<Vec<Question>, Error> {
+        match sqlx::query("SELECT * from questions WHERE id > $1 ORDER BY id LIMIT $2")
+            .bind(after_id)
+            .bind(limit)
+            .map(map_question)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(questions) => Ok(questions),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_answers_of_questions(&self, question_ids: Vec<i32>) -> Result<Vec<Answer>, Error> {
+        match sqlx::query("SELECT * from answers WHERE question_id = ANY($1) ORDER BY id")
+            .bind(question_ids)
+            .map(map_answer)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn add_question(
+        &self,
+        question: NewQuestion,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "INSERT INTO questions (title, content, tags, account_id, status)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING id, title, content, tags, account_id, status, score, accepted_answer_id,
+                    comment_count",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(question.tags)
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(map_question)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn question_owner(&self, question_id: i32) -> Result<Option<AccountId>, Error> {
+        match sqlx::query("SELECT account_id from questions where id = $1")
+            .bind(question_id)
+            .map(|row: PgRow| row.get::<Option<i32>, _>("account_id").map(AccountId))
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(owner)) => Ok(owner),
+            Ok(None) => Err(Error::QuestionNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        session: &Session,
+    ) -> Result<Question, Error> {
+        self.ensure_question_owner(question_id, session).await?;
+
+        match sqlx::query(
+            "UPDATE questions
+                SET title = $1, content = $2, tags = $3,
+                    status = CASE WHEN status IN ('pending', 'rejected') THEN status ELSE $4 END
+                WHERE id = $5
+                RETURNING id, title, content, tags, account_id, status, score, accepted_answer_id,
+                    comment_count",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(question.tags)
+        .bind(question.status.as_str())
+        .bind(question_id)
+        .map(map_question)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(question) => Ok(question),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn delete_question(&self, question_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_question_owner(question_id, session).await?;
+
+        match sqlx::query("DELETE FROM questions WHERE id = $1")
+            .bind(question_id)
+            .execute(&self.connection)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn add_answer(
+        &self,
+        answer: NewAnswer,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "INSERT INTO answers (content, question_id, account_id, status)
+             VALUES ($1, $2, $3, $4)
+             RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(answer.content)
+        .bind(answer.question_id.0)
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(map_answer)
+        .fetch_one(&self.connection)
+        .await
+        {
+            Ok(answer) => Ok(answer),
+            // The question is the only row the answer refers to which the caller picks
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_foreign_key_violation()) =>
+            {
+                Err(Error::QuestionNotFound)
+            }
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_answer(&self, answer_id: i32) -> Result<Answer, Error> {
+        match sqlx::query("SELECT * from answers WHERE id = $1")
+            .bind(answer_id)
+            .map(map_answer)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn answer_owner(&self, answer_id: i32) -> Result<Option<AccountId>, Error> {
+        match sqlx::query("SELECT account_id from answers where id = $1")
+            .bind(answer_id)
+            .map(|row: PgRow| row.get::<Option<i32>, _>("account_id").map(AccountId))
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(owner)) => Ok(owner),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        session: &Session,
+    ) -> Result<Answer, Error> {
+        self.ensure_answer_owner(answer_id, session).await?;
+
+        match sqlx::query(
+            "UPDATE answers
+                SET content = $1,
+                    status = CASE WHEN status IN ('pending', 'rejected') THEN status ELSE $2 END
+                WHERE id = $3
+                RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(answer.content)
+        .bind(answer.status.as_str())
+        .bind(answer_id)
+        .map(map_answer)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn delete_answer(&self, answer_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_answer_owner(answer_id, session).await?;
+
+        match sqlx::query("DELETE FROM answers WHERE id = $1")
+            .bind(answer_id)
+            .execute(&self.connection)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_comments(
+        &self,
+        target: &CommentTarget,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Comment>, Error> {
+        match sqlx::query(
+            "SELECT * from comments
+             WHERE (question_id = $1 OR answer_id = $2) AND status IN ($3, $4)
+             ORDER BY id LIMIT $5 OFFSET $6",
+        )
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .bind(limit)
+        .bind(offset)
+        .map(map_comment)
+        .fetch_all(&self.connection)
+        .await
+        {
+            Ok(comments) => Ok(comments),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_comment(&self, comment_id: i32) -> Result<Comment, Error> {
+        match sqlx::query("SELECT * from comments WHERE id = $1")
+            .bind(comment_id)
+            .map(map_comment)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(comment)) => Ok(comment),
+            Ok(None) => Err(Error::CommentNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn add_comment(
+        &self,
+        comment: NewComment,
+        target: CommentTarget,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let comment = match sqlx::query(
+            "INSERT INTO comments (content, question_id, answer_id, account_id, status)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(comment.content)
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(map_comment)
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(comment) => comment,
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_foreign_key_violation()) =>
+            {
+                return Err(target.not_found())
+            }
+            Err(error) => return Err(query_error(error)),
+        };
+
+        PostgresStore::refresh_comment_count(&mut tx, &comment.target)
+            .await
+            .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(comment)
+    }
+
+    async fn comment_owner(&self, comment_id: i32) -> Result<Option<AccountId>, Error> {
+        match sqlx::query("SELECT account_id from comments where id = $1")
+            .bind(comment_id)
+            .map(|row: PgRow| row.get::<Option<i32>, _>("account_id").map(AccountId))
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(owner)) => Ok(owner),
+            Ok(None) => Err(Error::CommentNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn update_comment(
+        &self,
+        comment: Comment,
+        comment_id: i32,
+        session: &Session,
+    ) -> Result<Comment, Error> {
+        self.ensure_comment_owner(comment_id, session).await?;
+
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let comment = sqlx::query(
+            "UPDATE comments
+                SET content = $1,
+                    status = CASE WHEN status IN ('pending', 'rejected') THEN status ELSE $2 END
+                WHERE id = $3
+                RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(comment.content)
+        .bind(comment.status.as_str())
+        .bind(comment_id)
+        .map(map_comment)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(query_error)?
+        .ok_or(Error::CommentNotFound)?;
+
+        PostgresStore::refresh_comment_count(&mut tx, &comment.target)
+            .await
+            .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(comment)
+    }
+
+    async fn delete_comment(&self, comment_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_comment_owner(comment_id, session).await?;
+
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let deleted = sqlx::query(
+            "DELETE FROM comments WHERE id = $1
+             RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(comment_id)
+        .map(map_comment)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(query_error)?;
+
+        if let Some(comment) = deleted {
+            PostgresStore::refresh_comment_count(&mut tx, &comment.target)
+                .await
+                .map_err(query_error)?;
+        }
+        tx.commit().await.map_err(query_error)?;
+        Ok(true)
+    }
+
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        match sqlx::query(
+            "INSERT INTO accounts (email, password, role)
+            VALUES ($1, $2, $3)",
+        )
+        .bind(account.email)
+        .bind(account.password)
+        .bind(account.role.as_str())
+        .execute(&self.connection)
+        .await
+        {
+            Ok(_) => Ok(true),
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_unique_violation()) =>
+            {
+                Err(Error::AccountAlreadyExists)
+            }
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        match sqlx::query("SELECT * from accounts where email = $1")
+            .bind(email)
+            .map(|row: PgRow| Account {
+                id: Some(AccountId(row.get("id"))),
+                email: row.get("email"),
+                password: row.get("password"),
+                role: row.get::<String, _>("role").parse().unwrap_or_default(),
+                disabled: row.get("disabled"),
+            })
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<AccountProfile>, Error> {
+        match sqlx::query("SELECT id, email, role, disabled from accounts ORDER BY id")
+            .map(map_profile)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(accounts) => Ok(accounts),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn update_account_role(
+        &self,
+        account_id: AccountId,
+        role: Role,
+    ) -> Result<AccountProfile, Error> {
+        match sqlx::query(
+            "UPDATE accounts
+                SET role = $1
+                WHERE id = $2
+                RETURNING id, email, role, disabled",
+        )
+        .bind(role.as_str())
+        .bind(account_id.0)
+        .map(map_profile)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_account_profile(&self, account_id: AccountId) -> Result<AccountProfile, Error> {
+        match sqlx::query("SELECT id, email, role, disabled FROM accounts WHERE id = $1")
+            .bind(account_id.0)
+            .map(map_profile)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn set_account_disabled(
+        &self,
+        account_id: AccountId,
+        disabled: bool,
+    ) -> Result<AccountProfile, Error> {
+        match sqlx::query(
+            "UPDATE accounts
+                SET disabled = $1
+                WHERE id = $2
+                RETURNING id, email, role, disabled",
+        )
+        .bind(disabled)
+        .bind(account_id.0)
+        .map(map_profile)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn update_account_password(
+        &self,
+        account_id: AccountId,
+        password: String,
+    ) -> Result<AccountProfile, Error> {
+        match sqlx::query(
+            "UPDATE accounts
+                SET password = $1
+                WHERE id = $2
+                RETURNING id, email, role, disabled",
+        )
+        .bind(password)
+        .bind(account_id.0)
+        .map(map_profile)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_questions_by_status(
+        &self,
+        status: ModerationStatus,
+    ) -> Result<Vec<Question>, Error> {
+        match sqlx::query("SELECT * from questions WHERE status = $1 ORDER BY id")
+            .bind(status.as_str())
+            .map(map_question)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(questions) => Ok(questions),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn set_question_status(
+        &self,
+        question_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Question, Error> {
+        match sqlx::query(
+            "UPDATE questions
+                SET status = $1
+                WHERE id = $2
+                RETURNING id, title, content, tags, account_id, status, score, accepted_answer_id,
+                    comment_count",
+        )
+        .bind(status.as_str())
+        .bind(question_id)
+        .map(map_question)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(question)) => Ok(question),
+            Ok(None) => Err(Error::QuestionNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_answers_by_status(&self, status: ModerationStatus) -> Result<Vec<Answer>, Error> {
+        match sqlx::query("SELECT * from answers WHERE status = $1 ORDER BY id")
+            .bind(status.as_str())
+            .map(map_answer)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(answers) => Ok(answers),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn set_answer_status(
+        &self,
+        answer_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "UPDATE answers
+                SET status = $1
+                WHERE id = $2
+                RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(status.as_str())
+        .bind(answer_id)
+        .map(map_answer)
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn get_comments_by_status(
+        &self,
+        status: ModerationStatus,
+    ) -> Result<Vec<Comment>, Error> {
+        match sqlx::query("SELECT * from comments WHERE status = $1 ORDER BY id")
+            .bind(status.as_str())
+            .map(map_comment)
+            .fetch_all(&self.connection)
+            .await
+        {
+            Ok(comments) => Ok(comments),
+            Err(error) => {
+                tracing::event!(tracing::Level::ERROR, "{:?}", error);
+                Err(Error::DatabaseQueryError(error))
+            }
+        }
+    }
+
+    async fn set_comment_status(
+        &self,
+        comment_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let comment = sqlx::query(
+            "UPDATE comments
+                SET status = $1
+                WHERE id = $2
+                RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(status.as_str())
+        .bind(comment_id)
+        .map(map_comment)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(query_error)?
+        .ok_or(Error::CommentNotFound)?;
+
+        PostgresStore::refresh_comment_count(&mut tx, &comment.target)
+            .await
+            .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(comment)
+    }
+}
@@ -0,0 +1,149 @@
+//! Searching for the stores without full-text search. Every word of the search has to
+//! appear in the question or in one of its answers, but words are not stemmed and
+//! the operators of Postgres searches are taken as words.
+
+use crate::types::answer::Answer;
+use crate::types::question::Question;
+use crate::types::search::{SearchHit, SearchQuery};
+use chrono::{Days, NaiveDateTime};
+
+/// Number of words a snippet shows at most
+const SNIPPET_WORDS: usize = 20;
+
+/// Weight of the words found in the content of a question, compared to its title
+const CONTENT_WEIGHT: f32 = 0.4;
+
+/// Weight of the best matching answer, compared to the question itself
+const ANSWER_WEIGHT: f32 = 0.1;
+
+/// Mark the matches in the snippets of Postgres, `chr(2)` and `chr(3)` in its query. The query
+/// removes them from the text, and they get replaced by `<b>` and `</b>` after escaping it.
+const START_SEL: char = '\u{2}';
+const STOP_SEL: char = '\u{3}';
+
+/// The lowercase words of a search
+pub(crate) fn terms(q: &str) -> Vec<String> {
+    q.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The first moment a question may have been asked, and the first moment after the range
+pub(crate) fn bounds(query: &SearchQuery) -> (Option<NaiveDateTime>, Option<NaiveDateTime>) {
+    let from = query.from.and_then(|day| day.and_hms_opt(0, 0, 0));
+    let until = query
+        .to
+        .and_then(|day| day.checked_add_days(Days::new(1)))
+        .and_then(|day| day.and_hms_opt(0, 0, 0));
+    (from, until)
+}
+
+/// Binds a moment as text, which compares with `created_on` in SQLite and casts in Postgres
+pub(crate) fn timestamp(moment: NaiveDateTime) -> String {
+    moment.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+fn count(text: &str, term: &str) -> usize {
+    text.to_lowercase().matches(term).count()
+}
+
+fn contains_all(text: &str, terms: &[String]) -> bool {
+    let text = text.to_lowercase();
+    terms.iter().all(|term| text.contains(term.as_str()))
+}
+
+/// Escapes the text of a snippet, so the `<b>` around the matches is the only markup in it
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Turns a headline of Postgres with the matches between `START_SEL` and `STOP_SEL`
+/// into an escaped snippet with the matches in `<b>`
+pub(crate) fn headline(text: &str) -> String {
+    escape_html(text)
+        .replace(START_SEL, "<b>")
+        .replace(STOP_SEL, "</b>")
+}
+
+/// Words around the first match, the matching words wrapped in `<b>` like Postgres does
+fn snippet(text: &str, terms: &[String]) -> String {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let is_match = |word: &str| {
+        let word = word.to_lowercase();
+        terms.iter().any(|term| word.contains(term.as_str()))
+    };
+    let first = words.iter().position(|word| is_match(word)).unwrap_or(0);
+    let start = first.saturating_sub(SNIPPET_WORDS / 4);
+
+    words[start..]
+        .iter()
+        .take(SNIPPET_WORDS)
+        .map(|word| match is_match(word) {
+            true => format!("<b>{}</b>", escape_html(word)),
+            false => escape_html(word),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Matches a question and its visible answers, the snippet comes from the question
+/// if all words are found there, from the best matching answer otherwise
+pub(crate) fn hit(question: Question, answers: &[Answer], terms: &[String]) -> Option<SearchHit> {
+    let text = format!("{} {}", question.title, question.content);
+    let question_rank = contains_all(&text, terms).then(|| {
+        terms
+            .iter()
+            .map(|term| {
+                count(&question.title, term) as f32
+                    + CONTENT_WEIGHT * count(&question.content, term) as f32
+            })
+            .sum::<f32>()
+    });
+    let best_answer = answers
+        .iter()
+        .filter(|answer| contains_all(&answer.content, terms))
+        .map(|answer| {
+            let rank: usize = terms.iter().map(|term| count(&answer.content, term)).sum();
+            (answer, rank as f32)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    let snippet = match (question_rank, best_answer) {
+        (Some(_), _) => snippet(&text, terms),
+        (None, Some((answer, _))) => snippet(&answer.content, terms),
+        (None, None) => return None,
+    };
+    let rank =
+        question_rank.unwrap_or(0.0) + ANSWER_WEIGHT * best_answer.map_or(0.0, |(_, rank)| rank);
+    Some(SearchHit {
+        question,
+        rank,
+        snippet,
+    })
+}
+
+/// Puts the best matches first, questions with the same rank in the order they were asked
+pub(crate) fn page(mut hits: Vec<SearchHit>, limit: Option<i32>, offset: i32) -> Vec<SearchHit> {
+    hits.sort_by(|a, b| {
+        b.rank
+            .total_cmp(&a.rank)
+            .then(a.question.id.0.cmp(&b.question.id.0))
+    });
+    let hits = hits.into_iter().skip(offset.max(0) as usize);
+    match limit {
+        Some(limit) => hits.take(limit.max(0) as usize).collect(),
+        None => hits.collect(),
+    }
+}
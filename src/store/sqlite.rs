@@ -0,0 +1,1251 @@
+use crate::store::{search, Repository};
+use crate::types::account::{Account, AccountId, AccountProfile, Role, Session};
+use crate::types::answer::{Answer, AnswerId, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentId, CommentTarget, NewComment};
+use crate::types::moderation::ModerationStatus;
+use crate::types::pagination::{Cursor, Page};
+use crate::types::question::{NewQuestion, Question, QuestionFilter, QuestionId, QuestionSort};
+use crate::types::search::{SearchHit, SearchQuery};
+use crate::types::tag::{TagCount, TagMatch};
+use crate::types::vote::Vote;
+use async_trait::async_trait;
+use handle_errors::Error;
+use sqlx::query::Query;
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow,
+};
+use sqlx::{Row, Sqlite, SqliteConnection};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Columns of a question, the tags get collected from `question_tags` into a JSON array
+const QUESTION_COLUMNS: &str = "id, title, content, account_id, status, score, accepted_answer_id,
+    comment_count,
+    (SELECT json_group_array(tag) FROM
+        (SELECT tag FROM question_tags WHERE question_id = questions.id ORDER BY position)
+    ) AS tags";
+
+fn map_question(row: SqliteRow) -> Question {
+    let tags: Vec<String> = serde_json::from_str(row.get("tags")).unwrap_or_default();
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        // A question without tags and one with an empty list look the same in `question_tags`
+        tags: if tags.is_empty() { None } else { Some(tags) },
+        account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+        status: row.get::<String, _>("status").parse().unwrap_or_default(),
+        score: row.get("score"),
+        accepted_answer_id: row
+            .get::<Option<i32>, _>("accepted_answer_id")
+            .map(AnswerId),
+        comment_count: row.get("comment_count"),
+    }
+}
+
+fn map_answer(row: SqliteRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("question_id")),
+        account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+        status: row.get::<String, _>("status").parse().unwrap_or_default(),
+        score: row.get("score"),
+        comment_count: row.get("comment_count"),
+    }
+}
+
+fn map_comment(row: SqliteRow) -> Comment {
+    let target = match row.get::<Option<i32>, _>("question_id") {
+        Some(id) => CommentTarget::Question(QuestionId(id)),
+        None => CommentTarget::Answer(AnswerId(row.get("answer_id"))),
+    };
+    Comment {
+        id: CommentId(row.get("id")),
+        content: row.get("content"),
+        target,
+        account_id: row.get::<Option<i32>, _>("account_id").map(AccountId),
+        status: row.get::<String, _>("status").parse().unwrap_or_default(),
+    }
+}
+
+fn map_profile(row: SqliteRow) -> AccountProfile {
+    AccountProfile {
+        id: AccountId(row.get("id")),
+        email: row.get("email"),
+        role: row.get::<String, _>("role").parse().unwrap_or_default(),
+        disabled: row.get("disabled"),
+    }
+}
+
+/// SQLite commits a statement with `RETURNING` once it ran to completion. Reading only the
+/// first row leaves the commit to whenever the statement gets reset, which may never happen
+/// if the process exits right after, so these statements are always read completely.
+fn first<T>(rows: Vec<T>) -> Option<T> {
+    rows.into_iter().next()
+}
+
+fn query_error(error: sqlx::Error) -> Error {
+    tracing::event!(tracing::Level::ERROR, "{:?}", error);
+    Error::DatabaseQueryError(error)
+}
+
+/// The visible questions of a filter with the value they get sorted by as `sort_key`,
+/// `bind_filter` binds `?1` to `?9`. The parameters are numbered as some are needed more than once.
+fn filtered_questions(filter: &QuestionFilter) -> String {
+    let sort_key = match filter.sort {
+        QuestionSort::Oldest | QuestionSort::Newest => "NULL",
+        QuestionSort::MostAnswered => {
+            "(SELECT count(*) FROM answers
+              WHERE answers.question_id = questions.id AND answers.status IN (?1, ?2))"
+        }
+        // In whole seconds, so the key fits into a cursor.
+        // max() of several values is NULL as soon as one of them is
+        QuestionSort::RecentlyActive => {
+            "CAST(strftime('%s', max(created_on, coalesce((SELECT max(answers.created_on)
+              FROM answers
+              WHERE answers.question_id = questions.id AND answers.status IN (?1, ?2)),
+              created_on))) AS INTEGER)"
+        }
+        QuestionSort::MostVoted => "score",
+    };
+
+    // A question needs one of the tags, or as many as the filter has
+    format!(
+        "SELECT {}, {} AS sort_key FROM questions
+         WHERE status IN (?1, ?2)
+            AND (?3 = 0 OR (SELECT count(DISTINCT tag) FROM question_tags
+                WHERE question_id = questions.id
+                    AND tag IN (SELECT value FROM json_each(?4))) >= ?5)
+            AND (?6 IS NULL OR account_id = ?6)
+            AND (?7 IS NULL OR created_on >= ?7)
+            AND (?8 IS NULL OR created_on < ?8)
+            AND (NOT ?9 OR NOT EXISTS (SELECT 1 FROM answers
+                WHERE answers.question_id = questions.id AND answers.status IN (?1, ?2)))",
+        QUESTION_COLUMNS, sort_key
+    )
+}
+
+fn bind_filter<'q>(
+    query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    filter: &QuestionFilter,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    let required = match filter.tag_match {
+        TagMatch::Any => 1,
+        TagMatch::All => filter.tags.len(),
+    };
+    query
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .bind(filter.tags.len() as i64)
+        .bind(serde_json::to_string(&filter.tags).expect("tags serialize to JSON"))
+        .bind(required as i64)
+        .bind(filter.author.as_ref().map(|author| author.0))
+        .bind(filter.created_after.map(search::timestamp))
+        .bind(filter.created_before.map(search::timestamp))
+        .bind(filter.unanswered)
+}
+
+/// The answer the question `?1` accepted
+const ACCEPTED: &str = "(SELECT accepted_answer_id FROM questions WHERE questions.id = ?1)";
+
+/// The `ORDER BY` of the answers to the question `?1`, the accepted answer first
+fn answer_order(sort: AnswerSort) -> String {
+    let order = match sort {
+        AnswerSort::Oldest => "id",
+        AnswerSort::MostVoted => "score DESC, id",
+    };
+    format!("id IS NOT {}, {}", ACCEPTED, order)
+}
+
+/// Stores everything in a single SQLite file, for deployments without a database server
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    pub connection: SqlitePool,
+}
+
+impl SqliteStore {
+    /// The questions of a filter in its order with their cursors, after the cursor if there is one
+    async fn select_questions(
+        &self,
+        filter: &QuestionFilter,
+        after: Option<&Cursor>,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<(Question, Cursor)>, Error> {
+        let (follows, order) = match filter.sort {
+            QuestionSort::Oldest => ("id > ?10", "id"),
+            QuestionSort::Newest => ("id < ?10", "id DESC"),
+            _ => (
+                "(sort_key < ?11 OR (sort_key = ?11 AND id > ?10))",
+                "sort_key DESC, id",
+            ),
+        };
+        // A negative limit means no limit in SQLite
+        let sql = format!(
+            "SELECT * FROM ({}) AS filtered
+             WHERE (?10 IS NULL OR {})
+             ORDER BY {} LIMIT ?12 OFFSET ?13",
+            filtered_questions(filter),
+            follows,
+            order
+        );
+
+        bind_filter(sqlx::query(&sql), filter)
+            .bind(after.map(|after| after.id))
+            .bind(after.and_then(|after| after.key))
+            .bind(limit.unwrap_or(-1))
+            .bind(offset)
+            .map(|row: SqliteRow| {
+                let cursor = Cursor {
+                    key: row.get("sort_key"),
+                    id: row.get("id"),
+                };
+                (map_question(row), cursor)
+            })
+            .fetch_all(&self.connection)
+            .await
+            .map_err(query_error)
+    }
+
+    pub async fn new(db_url: &str, max_connections: u32) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(db_url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+        // Every connection to `sqlite::memory:` opens a database of its own
+        let max_connections = if db_url.contains(":memory:") {
+            1
+        } else {
+            max_connections
+        };
+        let db_pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
+        Ok(SqliteStore {
+            connection: db_pool,
+        })
+    }
+
+    async fn fetch_question(
+        connection: &mut SqliteConnection,
+        question_id: i32,
+    ) -> Result<Option<Question>, sqlx::Error> {
+        sqlx::query(&format!(
+            "SELECT {} FROM questions WHERE id = ?",
+            QUESTION_COLUMNS
+        ))
+        .bind(question_id)
+        .map(map_question)
+        .fetch_optional(connection)
+        .await
+    }
+
+    /// Counts the visible comments on the question or answer again, after one of them changed
+    async fn refresh_comment_count(
+        connection: &mut SqliteConnection,
+        target: &CommentTarget,
+    ) -> Result<(), sqlx::Error> {
+        let sql = match target {
+            CommentTarget::Question(_) => {
+                "UPDATE questions
+                    SET comment_count = (SELECT count(*) FROM comments
+                        WHERE question_id = ?1 AND status IN (?2, ?3))
+                    WHERE id = ?1"
+            }
+            CommentTarget::Answer(_) => {
+                "UPDATE answers
+                    SET comment_count = (SELECT count(*) FROM comments
+                        WHERE answer_id = ?1 AND status IN (?2, ?3))
+                    WHERE id = ?1"
+            }
+        };
+        sqlx::query(sql)
+            .bind(target.id())
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .execute(connection)
+            .await
+            .map(|_| ())
+    }
+
+    async fn replace_tags(
+        connection: &mut SqliteConnection,
+        question_id: i32,
+        tags: Option<Vec<String>>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM question_tags WHERE question_id = ?")
+            .bind(question_id)
+            .execute(&mut *connection)
+            .await?;
+        for (position, tag) in tags.unwrap_or_default().into_iter().enumerate() {
+            sqlx::query("INSERT INTO question_tags (question_id, position, tag) VALUES (?, ?, ?)")
+                .bind(question_id)
+                .bind(position as i64)
+                .bind(tag)
+                .execute(&mut *connection)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Repository for SqliteStore {
+    async fn get_questions(
+        &self,
+        filter: &QuestionFilter,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Question>, Error> {
+        let rows = self.select_questions(filter, None, limit, offset).await?;
+        Ok(rows.into_iter().map(|(question, _)| question).collect())
+    }
+
+    async fn get_question_page(
+        &self,
+        filter: &QuestionFilter,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Question>, Error> {
+        let rows = self
+            .select_questions(filter, after, Some(limit.saturating_add(1)), 0)
+            .await?;
+        Ok(Page::new(rows, limit))
+    }
+
+    async fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, Error> {
+        // Counting needs no sort values
+        let filter = QuestionFilter {
+            sort: QuestionSort::Oldest,
+            ..filter.clone()
+        };
+        let sql = format!(
+            "SELECT count(*) FROM ({}) AS filtered",
+            filtered_questions(&filter)
+        );
+        bind_filter(sqlx::query(&sql), &filter)
+            .map(|row: SqliteRow| row.get(0))
+            .fetch_one(&self.connection)
+            .await
+            .map_err(query_error)
+    }
+
+    async fn get_tags(&self) -> Result<Vec<TagCount>, Error> {
+        sqlx::query(
+            "SELECT tag, count(DISTINCT question_id) AS questions
+             FROM question_tags JOIN questions ON questions.id = question_tags.question_id
+             WHERE questions.status IN (?, ?)
+             GROUP BY tag
+             ORDER BY questions DESC, tag",
+        )
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .map(|row: SqliteRow| TagCount {
+            tag: row.get("tag"),
+            questions: row.get("questions"),
+        })
+        .fetch_all(&self.connection)
+        .await
+        .map_err(query_error)
+    }
+
+    async fn get_question(&self, question_id: i32) -> Result<Question, Error> {
+        let mut connection = self.connection.acquire().await.map_err(query_error)?;
+        SqliteStore::fetch_question(&mut connection, question_id)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::QuestionNotFound)
+    }
+
+    async fn get_answers(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Answer>, Error> {
+        let sql = format!(
+            "SELECT * FROM answers WHERE question_id = ?1 AND status IN (?2, ?3)
+             ORDER BY {} LIMIT ?4 OFFSET ?5",
+            answer_order(sort)
+        );
+        sqlx::query(&sql)
+            .bind(question_id)
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .bind(limit.unwrap_or(-1))
+            .bind(offset)
+            .map(map_answer)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(query_error)
+    }
+
+    async fn get_answer_page(
+        &self,
+        question_id: i32,
+        sort: AnswerSort,
+        after: Option<&Cursor>,
+        limit: i32,
+    ) -> Result<Page<Answer>, Error> {
+        let follows = match sort {
+            AnswerSort::Oldest => "id > ?4",
+            AnswerSort::MostVoted => "(score < ?5 OR (score = ?5 AND id > ?4))",
+        };
+        // Every other answer comes after the accepted one, which comes after none
+        let sql = format!(
+            "SELECT * FROM answers
+             WHERE question_id = ?1 AND status IN (?2, ?3)
+                AND (?4 IS NULL OR (id IS NOT {accepted} AND (?4 = {accepted} OR {follows})))
+             ORDER BY {order} LIMIT ?6",
+            accepted = ACCEPTED,
+            follows = follows,
+            order = answer_order(sort)
+        );
+        sqlx::query(&sql)
+            .bind(question_id)
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .bind(after.map(|after| after.id))
+            .bind(after.and_then(|after| after.key))
+            .bind(limit.saturating_add(1))
+            .map(move |row: SqliteRow| {
+                let cursor = Cursor {
+                    key: (sort == AnswerSort::MostVoted)
+                        .then(|| i64::from(row.get::<i32, _>("score"))),
+                    id: row.get("id"),
+                };
+                (map_answer(row), cursor)
+            })
+            .fetch_all(&self.connection)
+            .await
+            .map(|rows| Page::new(rows, limit))
+            .map_err(query_error)
+    }
+
+    async fn count_answers(&self, question_id: i32) -> Result<i64, Error> {
+        sqlx::query("SELECT count(*) FROM answers WHERE question_id = ? AND status IN (?, ?)")
+            .bind(question_id)
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str())
+            .map(|row: SqliteRow| row.get(0))
+            .fetch_one(&self.connection)
+            .await
+            .map_err(query_error)
+    }
+
+    async fn vote_question(
+        &self,
+        question_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Question, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        // Writing first takes the lock on the database before the score gets summed up
+        let change = match vote {
+            Some(vote) => sqlx::query(
+                "INSERT INTO question_votes (question_id, account_id, value) VALUES (?, ?, ?)
+                 ON CONFLICT (question_id, account_id) DO UPDATE SET value = excluded.value",
+            )
+            .bind(question_id)
+            .bind(account_id.0)
+            .bind(vote.value()),
+            None => {
+                sqlx::query("DELETE FROM question_votes WHERE question_id = ? AND account_id = ?")
+                    .bind(question_id)
+                    .bind(account_id.0)
+            }
+        };
+        match change.execute(&mut *tx).await {
+            Ok(_) => (),
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_foreign_key_violation()) =>
+            {
+                return Err(Error::QuestionNotFound)
+            }
+            Err(error) => return Err(query_error(error)),
+        }
+
+        let updated = sqlx::query(
+            "UPDATE questions
+                SET score = (SELECT coalesce(sum(value), 0) FROM question_votes WHERE question_id = ?1)
+                WHERE id = ?1",
+        )
+        .bind(question_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(query_error)?;
+        if updated.rows_affected() == 0 {
+            return Err(Error::QuestionNotFound);
+        }
+
+        let question = SqliteStore::fetch_question(&mut tx, question_id)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::QuestionNotFound)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(question)
+    }
+
+    async fn vote_answer(
+        &self,
+        answer_id: i32,
+        account_id: AccountId,
+        vote: Option<Vote>,
+    ) -> Result<Answer, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let change = match vote {
+            Some(vote) => sqlx::query(
+                "INSERT INTO answer_votes (answer_id, account_id, value) VALUES (?, ?, ?)
+                 ON CONFLICT (answer_id, account_id) DO UPDATE SET value = excluded.value",
+            )
+            .bind(answer_id)
+            .bind(account_id.0)
+            .bind(vote.value()),
+            None => sqlx::query("DELETE FROM answer_votes WHERE answer_id = ? AND account_id = ?")
+                .bind(answer_id)
+                .bind(account_id.0),
+        };
+        match change.execute(&mut *tx).await {
+            Ok(_) => (),
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_foreign_key_violation()) =>
+            {
+                return Err(Error::AnswerNotFound)
+            }
+            Err(error) => return Err(query_error(error)),
+        }
+
+        let answer = sqlx::query(
+            "UPDATE answers
+                SET score = (SELECT coalesce(sum(value), 0) FROM answer_votes WHERE answer_id = ?1)
+                WHERE id = ?1
+                RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(answer_id)
+        .map(map_answer)
+        .fetch_all(&mut *tx)
+        .await
+        .map(first)
+        .map_err(query_error)?
+        .ok_or(Error::AnswerNotFound)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(answer)
+    }
+
+    async fn set_accepted_answer(
+        &self,
+        question_id: i32,
+        answer_id: Option<i32>,
+    ) -> Result<Question, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let updated = sqlx::query(
+            "UPDATE questions SET accepted_answer_id = ?2
+             WHERE id = ?1 AND (?2 IS NULL
+                OR EXISTS (SELECT 1 FROM answers WHERE id = ?2 AND question_id = ?1))",
+        )
+        .bind(question_id)
+        .bind(answer_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(query_error)?;
+
+        let question = SqliteStore::fetch_question(&mut tx, question_id)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::QuestionNotFound)?;
+        // The question is there, so the answer is not one to it
+        if updated.rows_affected() == 0 {
+            return Err(Error::AnswerNotFound);
+        }
+        tx.commit().await.map_err(query_error)?;
+        Ok(question)
+    }
+
+    async fn search(
+        &self,
+        query: &SearchQuery,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<SearchHit>, Error> {
+        let terms = search::terms(&query.q);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (from, until) = search::bounds(query);
+
+        // Narrows the questions down, whether all words are in the same text gets checked after
+        let mut sql = format!(
+            "SELECT {} FROM questions WHERE status IN (?, ?)",
+            QUESTION_COLUMNS
+        );
+        for _ in &query.tags {
+            sql.push_str(
+                " AND EXISTS (SELECT 1 FROM question_tags
+                    WHERE question_id = questions.id AND tag = ?)",
+            );
+        }
+        if from.is_some() {
+            sql.push_str(" AND created_on >= ?");
+        }
+        if until.is_some() {
+            sql.push_str(" AND created_on < ?");
+        }
+        for _ in &terms {
+            sql.push_str(
+                " AND (instr(lower(title || ' ' || content), ?) > 0
+                    OR EXISTS (SELECT 1 FROM answers WHERE question_id = questions.id
+                        AND status IN (?, ?) AND instr(lower(content), ?) > 0))",
+            );
+        }
+        sql.push_str(" ORDER BY id");
+
+        let mut candidates = sqlx::query(&sql)
+            .bind(ModerationStatus::Published.as_str())
+            .bind(ModerationStatus::Unchecked.as_str());
+        for tag in &query.tags {
+            candidates = candidates.bind(tag);
+        }
+        for moment in [from, until].into_iter().flatten() {
+            candidates = candidates.bind(search::timestamp(moment));
+        }
+        for term in &terms {
+            candidates = candidates
+                .bind(term)
+                .bind(ModerationStatus::Published.as_str())
+                .bind(ModerationStatus::Unchecked.as_str())
+                .bind(term);
+        }
+        let questions: Vec<Question> = candidates
+            .map(map_question)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(query_error)?;
+
+        let ids: Vec<i32> = questions.iter().map(|q| q.id.0).collect();
+        let mut answers: HashMap<i32, Vec<Answer>> = HashMap::new();
+        for answer in sqlx::query(
+            "SELECT * FROM answers
+             WHERE question_id IN (SELECT value FROM json_each(?)) AND status IN (?, ?)
+             ORDER BY id",
+        )
+        .bind(serde_json::to_string(&ids).expect("ids serialize to JSON"))
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .map(map_answer)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(query_error)?
+        {
+            answers
+                .entry(answer.question_id.0)
+                .or_default()
+                .push(answer);
+        }
+
+        let hits = questions
+            .into_iter()
+            .filter_map(|question| {
+                let answers = answers.remove(&question.id.0).unwrap_or_default();
+                search::hit(question, &answers, &terms)
+            })
+            .collect();
+        Ok(search::page(hits, limit, offset))
+    }
+
+    async fn get_questions_after(&self, after_id: i32, limit: i32) -> Result<Vec<Question>, Error> {
+        sqlx::query(&format!(
+            "SELECT {} FROM questions WHERE id > ? ORDER BY id LIMIT ?",
+            QUESTION_COLUMNS
+        ))
+        .bind(after_id)
+        .bind(limit)
+        .map(map_question)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(query_error)
+    }
+
+    async fn get_answers_of_questions(&self, question_ids: Vec<i32>) -> Result<Vec<Answer>, Error> {
+        // SQLite binds no arrays, the ids get passed as a JSON array instead
+        sqlx::query(
+            "SELECT * FROM answers
+             WHERE question_id IN (SELECT value FROM json_each(?))
+             ORDER BY id",
+        )
+        .bind(serde_json::to_string(&question_ids).expect("ids serialize to JSON"))
+        .map(map_answer)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(query_error)
+    }
+
+    async fn add_question(
+        &self,
+        question: NewQuestion,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Question, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let id: i32 = sqlx::query(
+            "INSERT INTO questions (title, content, account_id, status)
+             VALUES (?, ?, ?, ?)
+             RETURNING id",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(|row: SqliteRow| row.get("id"))
+        .fetch_all(&mut *tx)
+        .await
+        .and_then(|rows| first(rows).ok_or(sqlx::Error::RowNotFound))
+        .map_err(query_error)?;
+
+        SqliteStore::replace_tags(&mut tx, id, question.tags)
+            .await
+            .map_err(query_error)?;
+        let question = SqliteStore::fetch_question(&mut tx, id)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::QuestionNotFound)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(question)
+    }
+
+    async fn question_owner(&self, question_id: i32) -> Result<Option<AccountId>, Error> {
+        match sqlx::query("SELECT account_id FROM questions WHERE id = ?")
+            .bind(question_id)
+            .map(|row: SqliteRow| row.get::<Option<i32>, _>("account_id").map(AccountId))
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(owner)) => Ok(owner),
+            Ok(None) => Err(Error::QuestionNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn update_question(
+        &self,
+        question: Question,
+        question_id: i32,
+        session: &Session,
+    ) -> Result<Question, Error> {
+        self.ensure_question_owner(question_id, session).await?;
+
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        sqlx::query(
+            "UPDATE questions SET title = ?, content = ?,
+                status = CASE WHEN status IN ('pending', 'rejected') THEN status ELSE ? END
+             WHERE id = ?",
+        )
+        .bind(question.title)
+        .bind(question.content)
+        .bind(question.status.as_str())
+        .bind(question_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(query_error)?;
+
+        SqliteStore::replace_tags(&mut tx, question_id, question.tags)
+            .await
+            .map_err(query_error)?;
+        let question = SqliteStore::fetch_question(&mut tx, question_id)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::QuestionNotFound)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(question)
+    }
+
+    async fn delete_question(&self, question_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_question_owner(question_id, session).await?;
+
+        sqlx::query("DELETE FROM questions WHERE id = ?")
+            .bind(question_id)
+            .execute(&self.connection)
+            .await
+            .map(|_| true)
+            .map_err(query_error)
+    }
+
+    async fn add_answer(
+        &self,
+        answer: NewAnswer,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "INSERT INTO answers (content, question_id, account_id, status)
+             VALUES (?, ?, ?, ?)
+             RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(answer.content)
+        .bind(answer.question_id.0)
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(map_answer)
+        .fetch_all(&self.connection)
+        .await
+        .and_then(|rows| first(rows).ok_or(sqlx::Error::RowNotFound))
+        {
+            Ok(answer) => Ok(answer),
+            // The question is the only row the answer refers to which the caller picks
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_foreign_key_violation()) =>
+            {
+                Err(Error::QuestionNotFound)
+            }
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn get_answer(&self, answer_id: i32) -> Result<Answer, Error> {
+        match sqlx::query("SELECT * FROM answers WHERE id = ?")
+            .bind(answer_id)
+            .map(map_answer)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn answer_owner(&self, answer_id: i32) -> Result<Option<AccountId>, Error> {
+        match sqlx::query("SELECT account_id FROM answers WHERE id = ?")
+            .bind(answer_id)
+            .map(|row: SqliteRow| row.get::<Option<i32>, _>("account_id").map(AccountId))
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(owner)) => Ok(owner),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn update_answer(
+        &self,
+        answer: Answer,
+        answer_id: i32,
+        session: &Session,
+    ) -> Result<Answer, Error> {
+        self.ensure_answer_owner(answer_id, session).await?;
+
+        match sqlx::query(
+            "UPDATE answers SET content = ?,
+                status = CASE WHEN status IN ('pending', 'rejected') THEN status ELSE ? END
+             WHERE id = ?
+             RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(answer.content)
+        .bind(answer.status.as_str())
+        .bind(answer_id)
+        .map(map_answer)
+        .fetch_all(&self.connection)
+        .await
+        .map(first)
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn delete_answer(&self, answer_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_answer_owner(answer_id, session).await?;
+
+        sqlx::query("DELETE FROM answers WHERE id = ?")
+            .bind(answer_id)
+            .execute(&self.connection)
+            .await
+            .map(|_| true)
+            .map_err(query_error)
+    }
+
+    async fn get_comments(
+        &self,
+        target: &CommentTarget,
+        limit: Option<i32>,
+        offset: i32,
+    ) -> Result<Vec<Comment>, Error> {
+        sqlx::query(
+            "SELECT * FROM comments
+             WHERE (question_id = ? OR answer_id = ?) AND status IN (?, ?)
+             ORDER BY id LIMIT ? OFFSET ?",
+        )
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .bind(ModerationStatus::Published.as_str())
+        .bind(ModerationStatus::Unchecked.as_str())
+        .bind(limit.unwrap_or(-1))
+        .bind(offset)
+        .map(map_comment)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(query_error)
+    }
+
+    async fn get_comment(&self, comment_id: i32) -> Result<Comment, Error> {
+        match sqlx::query("SELECT * FROM comments WHERE id = ?")
+            .bind(comment_id)
+            .map(map_comment)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(comment)) => Ok(comment),
+            Ok(None) => Err(Error::CommentNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn add_comment(
+        &self,
+        comment: NewComment,
+        target: CommentTarget,
+        account_id: AccountId,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let comment = match sqlx::query(
+            "INSERT INTO comments (content, question_id, answer_id, account_id, status)
+             VALUES (?, ?, ?, ?, ?)
+             RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(comment.content)
+        .bind(target.question_id())
+        .bind(target.answer_id())
+        .bind(account_id.0)
+        .bind(status.as_str())
+        .map(map_comment)
+        .fetch_all(&mut *tx)
+        .await
+        .and_then(|rows| first(rows).ok_or(sqlx::Error::RowNotFound))
+        {
+            Ok(comment) => comment,
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_foreign_key_violation()) =>
+            {
+                return Err(target.not_found())
+            }
+            Err(error) => return Err(query_error(error)),
+        };
+
+        SqliteStore::refresh_comment_count(&mut tx, &comment.target)
+            .await
+            .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(comment)
+    }
+
+    async fn comment_owner(&self, comment_id: i32) -> Result<Option<AccountId>, Error> {
+        match sqlx::query("SELECT account_id FROM comments WHERE id = ?")
+            .bind(comment_id)
+            .map(|row: SqliteRow| row.get::<Option<i32>, _>("account_id").map(AccountId))
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(owner)) => Ok(owner),
+            Ok(None) => Err(Error::CommentNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn update_comment(
+        &self,
+        comment: Comment,
+        comment_id: i32,
+        session: &Session,
+    ) -> Result<Comment, Error> {
+        self.ensure_comment_owner(comment_id, session).await?;
+
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let comment = sqlx::query(
+            "UPDATE comments SET content = ?,
+                status = CASE WHEN status IN ('pending', 'rejected') THEN status ELSE ? END
+             WHERE id = ?
+             RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(comment.content)
+        .bind(comment.status.as_str())
+        .bind(comment_id)
+        .map(map_comment)
+        .fetch_all(&mut *tx)
+        .await
+        .map(first)
+        .map_err(query_error)?
+        .ok_or(Error::CommentNotFound)?;
+
+        SqliteStore::refresh_comment_count(&mut tx, &comment.target)
+            .await
+            .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(comment)
+    }
+
+    async fn delete_comment(&self, comment_id: i32, session: &Session) -> Result<bool, Error> {
+        self.ensure_comment_owner(comment_id, session).await?;
+
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let deleted = sqlx::query(
+            "DELETE FROM comments WHERE id = ?
+             RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(comment_id)
+        .map(map_comment)
+        .fetch_all(&mut *tx)
+        .await
+        .map(first)
+        .map_err(query_error)?;
+
+        if let Some(comment) = deleted {
+            SqliteStore::refresh_comment_count(&mut tx, &comment.target)
+                .await
+                .map_err(query_error)?;
+        }
+        tx.commit().await.map_err(query_error)?;
+        Ok(true)
+    }
+
+    async fn add_account(&self, account: Account) -> Result<bool, Error> {
+        match sqlx::query("INSERT INTO accounts (email, password, role) VALUES (?, ?, ?)")
+            .bind(account.email)
+            .bind(account.password)
+            .bind(account.role.as_str())
+            .execute(&self.connection)
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(error)
+                if error
+                    .as_database_error()
+                    .is_some_and(|e| e.is_unique_violation()) =>
+            {
+                Err(Error::AccountAlreadyExists)
+            }
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn get_account(&self, email: String) -> Result<Account, Error> {
+        match sqlx::query(
+            "SELECT id, email, password, role, disabled FROM accounts WHERE email = ?",
+        )
+        .bind(email)
+        .map(|row: SqliteRow| Account {
+            id: Some(AccountId(row.get("id"))),
+            email: row.get("email"),
+            password: row.get("password"),
+            role: row.get::<String, _>("role").parse().unwrap_or_default(),
+            disabled: row.get("disabled"),
+        })
+        .fetch_optional(&self.connection)
+        .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn get_accounts(&self) -> Result<Vec<AccountProfile>, Error> {
+        sqlx::query("SELECT id, email, role, disabled FROM accounts ORDER BY id")
+            .map(map_profile)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(query_error)
+    }
+
+    async fn update_account_role(
+        &self,
+        account_id: AccountId,
+        role: Role,
+    ) -> Result<AccountProfile, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET role = ? WHERE id = ? RETURNING id, email, role, disabled",
+        )
+        .bind(role.as_str())
+        .bind(account_id.0)
+        .map(map_profile)
+        .fetch_all(&self.connection)
+        .await
+        .map(first)
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn get_account_profile(&self, account_id: AccountId) -> Result<AccountProfile, Error> {
+        match sqlx::query("SELECT id, email, role, disabled FROM accounts WHERE id = ?")
+            .bind(account_id.0)
+            .map(map_profile)
+            .fetch_optional(&self.connection)
+            .await
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn set_account_disabled(
+        &self,
+        account_id: AccountId,
+        disabled: bool,
+    ) -> Result<AccountProfile, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET disabled = ? WHERE id = ? RETURNING id, email, role, disabled",
+        )
+        .bind(disabled)
+        .bind(account_id.0)
+        .map(map_profile)
+        .fetch_all(&self.connection)
+        .await
+        .map(first)
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn update_account_password(
+        &self,
+        account_id: AccountId,
+        password: String,
+    ) -> Result<AccountProfile, Error> {
+        match sqlx::query(
+            "UPDATE accounts SET password = ? WHERE id = ? RETURNING id, email, role, disabled",
+        )
+        .bind(password)
+        .bind(account_id.0)
+        .map(map_profile)
+        .fetch_all(&self.connection)
+        .await
+        .map(first)
+        {
+            Ok(Some(account)) => Ok(account),
+            Ok(None) => Err(Error::AccountNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn get_questions_by_status(
+        &self,
+        status: ModerationStatus,
+    ) -> Result<Vec<Question>, Error> {
+        sqlx::query(&format!(
+            "SELECT {} FROM questions WHERE status = ? ORDER BY id",
+            QUESTION_COLUMNS
+        ))
+        .bind(status.as_str())
+        .map(map_question)
+        .fetch_all(&self.connection)
+        .await
+        .map_err(query_error)
+    }
+
+    async fn set_question_status(
+        &self,
+        question_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Question, Error> {
+        let mut connection = self.connection.acquire().await.map_err(query_error)?;
+        let updated = sqlx::query("UPDATE questions SET status = ? WHERE id = ?")
+            .bind(status.as_str())
+            .bind(question_id)
+            .execute(&mut *connection)
+            .await
+            .map_err(query_error)?;
+        if updated.rows_affected() == 0 {
+            return Err(Error::QuestionNotFound);
+        }
+        SqliteStore::fetch_question(&mut connection, question_id)
+            .await
+            .map_err(query_error)?
+            .ok_or(Error::QuestionNotFound)
+    }
+
+    async fn get_answers_by_status(&self, status: ModerationStatus) -> Result<Vec<Answer>, Error> {
+        sqlx::query("SELECT * FROM answers WHERE status = ? ORDER BY id")
+            .bind(status.as_str())
+            .map(map_answer)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(query_error)
+    }
+
+    async fn set_answer_status(
+        &self,
+        answer_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Answer, Error> {
+        match sqlx::query(
+            "UPDATE answers SET status = ? WHERE id = ?
+             RETURNING id, content, question_id, account_id, status, score, comment_count",
+        )
+        .bind(status.as_str())
+        .bind(answer_id)
+        .map(map_answer)
+        .fetch_all(&self.connection)
+        .await
+        .map(first)
+        {
+            Ok(Some(answer)) => Ok(answer),
+            Ok(None) => Err(Error::AnswerNotFound),
+            Err(error) => Err(query_error(error)),
+        }
+    }
+
+    async fn get_comments_by_status(
+        &self,
+        status: ModerationStatus,
+    ) -> Result<Vec<Comment>, Error> {
+        sqlx::query("SELECT * FROM comments WHERE status = ? ORDER BY id")
+            .bind(status.as_str())
+            .map(map_comment)
+            .fetch_all(&self.connection)
+            .await
+            .map_err(query_error)
+    }
+
+    async fn set_comment_status(
+        &self,
+        comment_id: i32,
+        status: ModerationStatus,
+    ) -> Result<Comment, Error> {
+        let mut tx = self.connection.begin().await.map_err(query_error)?;
+        let comment = sqlx::query(
+            "UPDATE comments SET status = ? WHERE id = ?
+             RETURNING id, content, question_id, answer_id, account_id, status",
+        )
+        .bind(status.as_str())
+        .bind(comment_id)
+        .map(map_comment)
+        .fetch_all(&mut *tx)
+        .await
+        .map(first)
+        .map_err(query_error)?
+        .ok_or(Error::CommentNotFound)?;
+
+        SqliteStore::refresh_comment_count(&mut tx, &comment.target)
+            .await
+            .map_err(query_error)?;
+        tx.commit().await.map_err(query_error)?;
+        Ok(comment)
+    }
+}
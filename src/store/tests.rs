@@ -0,0 +1,1616 @@
+//! Behavior every `Repository` has to share. The suite runs against the memory and
+//! SQLite stores. The Postgres tests are ignored unless asked for with `--ignored`,
+//! they need `TEST_DATABASE_URL` to point to a database and fail without it.
+//! The Postgres database is not emptied, so the tests only look at the rows they create.
+
+use crate::store::memory::{LegacyQuestion, MemoryStore};
+use crate::store::migrations;
+use crate::store::postgres::PostgresStore;
+use crate::store::sqlite::SqliteStore;
+use crate::store::Store;
+use crate::types::account::{Account, AccountId, Role, Session};
+use crate::types::answer::{Answer, AnswerId, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentTarget, NewComment};
+use crate::types::moderation::ModerationStatus;
+use crate::types::question::{NewQuestion, Question, QuestionFilter, QuestionId, QuestionSort};
+use crate::types::search::{SearchHit, SearchQuery};
+use crate::types::tag::{TagCount, TagMatch};
+use crate::types::vote::Vote;
+use handle_errors::Error;
+use std::sync::Arc;
+
+async fn memory() -> Store {
+    Arc::new(MemoryStore::new())
+}
+
+async fn sqlite() -> Store {
+    let store = SqliteStore::new("sqlite::memory:", 1).await.unwrap();
+    migrations::SQLITE.run(&store.connection).await.unwrap();
+    Arc::new(store)
+}
+
+async fn postgres() -> Store {
+    let url = std::env::var("TEST_DATABASE_URL")
+        .expect("TEST_DATABASE_URL has to point to a Postgres database for the Postgres tests");
+    let store = PostgresStore::new(&url, 5).await.unwrap();
+    migrations::POSTGRES.run(&store.connection).await.unwrap();
+    Arc::new(store)
+}
+
+/// Creates an account with a unique address and returns a session for it
+async fn session(store: &Store, role: Role) -> Session {
+    let email = format!("{}@example.com", uuid::Uuid::new_v4());
+    store
+        .add_account(Account {
+            id: None,
+            email: email.clone(),
+            password: "hash".to_string(),
+            role,
+            disabled: false,
+        })
+        .await
+        .unwrap();
+    let account = store.get_account(email).await.unwrap();
+    Session {
+        exp: chrono::Utc::now() + chrono::Duration::hours(1),
+        account_id: account.id.unwrap(),
+        nbf: chrono::Utc::now(),
+        role,
+    }
+}
+
+fn new_question(tags: Option<Vec<&str>>) -> NewQuestion {
+    NewQuestion {
+        title: "How?".to_string(),
+        content: "Please help!".to_string(),
+        tags: tags.map(|tags| tags.into_iter().map(String::from).collect()),
+    }
+}
+
+fn contains(questions: &[Question], id: &QuestionId) -> bool {
+    questions.iter().any(|q| &q.id == id)
+}
+
+async fn adds_and_lists_questions(store: Store) {
+    let session = session(&store, Role::User).await;
+    let question = store
+        .add_question(
+            new_question(Some(vec!["rust", "warp"])),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(question.title, "How?");
+    assert_eq!(
+        question.tags,
+        Some(vec!["rust".to_string(), "warp".to_string()])
+    );
+    assert_eq!(question.account_id, Some(session.account_id));
+    assert_eq!(question.status, ModerationStatus::Published);
+
+    let questions = store
+        .get_questions(&QuestionFilter::default(), None, 0)
+        .await
+        .unwrap();
+    let listed = questions.iter().find(|q| q.id == question.id).unwrap();
+    assert_eq!(listed.tags, question.tags);
+
+    let untagged = store
+        .add_question(
+            new_question(None),
+            question.account_id.unwrap(),
+            ModerationStatus::Unchecked,
+        )
+        .await
+        .unwrap();
+    assert_eq!(untagged.tags, None);
+    assert!(contains(
+        &store
+            .get_questions(&QuestionFilter::default(), None, 0)
+            .await
+            .unwrap(),
+        &untagged.id
+    ));
+}
+
+async fn pages_through_questions(store: Store) {
+    let session = session(&store, Role::User).await;
+    for _ in 0..3 {
+        store
+            .add_question(
+                new_question(None),
+                session.account_id.clone(),
+                ModerationStatus::Published,
+            )
+            .await
+            .unwrap();
+    }
+
+    let all = store
+        .get_questions(&QuestionFilter::default(), None, 0)
+        .await
+        .unwrap();
+    let page = store
+        .get_questions(&QuestionFilter::default(), Some(2), 1)
+        .await
+        .unwrap();
+    assert_eq!(page.len(), 2);
+    // Questions come in the order they were added, the offset skips the oldest one
+    assert!(page[0].id.0 > all[0].id.0);
+    assert!(page[1].id.0 > page[0].id.0);
+}
+
+async fn hides_questions_until_published(store: Store) {
+    let session = session(&store, Role::User).await;
+    let question = store
+        .add_question(
+            new_question(None),
+            session.account_id,
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+
+    assert!(!contains(
+        &store
+            .get_questions(&QuestionFilter::default(), None, 0)
+            .await
+            .unwrap(),
+        &question.id
+    ));
+    let pending = store
+        .get_questions_by_status(ModerationStatus::Pending)
+        .await
+        .unwrap();
+    assert!(contains(&pending, &question.id));
+
+    let published = store
+        .set_question_status(question.id.0, ModerationStatus::Published)
+        .await
+        .unwrap();
+    assert_eq!(published.status, ModerationStatus::Published);
+    assert!(contains(
+        &store
+            .get_questions(&QuestionFilter::default(), None, 0)
+            .await
+            .unwrap(),
+        &question.id
+    ));
+}
+
+async fn edits_keep_questions_in_moderation(store: Store) {
+    let session = session(&store, Role::User).await;
+    let question = store
+        .add_question(
+            new_question(None),
+            session.account_id.clone(),
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+    store
+        .set_question_status(question.id.0, ModerationStatus::Rejected)
+        .await
+        .unwrap();
+
+    let mut update = question.clone();
+    update.title = "Why not?".to_string();
+    update.status = ModerationStatus::Published;
+    let edited = store
+        .update_question(update.clone(), question.id.0, &session)
+        .await
+        .unwrap();
+    assert_eq!(edited.title, "Why not?");
+    assert_eq!(edited.status, ModerationStatus::Rejected);
+    assert!(!contains(
+        &store
+            .get_questions(&QuestionFilter::default(), None, 0)
+            .await
+            .unwrap(),
+        &question.id
+    ));
+
+    // Only a moderator publishes it again, after that edits get checked as usual
+    store
+        .set_question_status(question.id.0, ModerationStatus::Published)
+        .await
+        .unwrap();
+    update.status = ModerationStatus::Pending;
+    let edited = store
+        .update_question(update, question.id.0, &session)
+        .await
+        .unwrap();
+    assert_eq!(edited.status, ModerationStatus::Pending);
+}
+
+async fn only_owners_and_moderators_change_questions(store: Store) {
+    let owner = session(&store, Role::User).await;
+    let other = session(&store, Role::User).await;
+    let moderator = session(&store, Role::Moderator).await;
+    let question = store
+        .add_question(
+            new_question(Some(vec!["rust"])),
+            owner.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+
+    let mut update = question.clone();
+    update.title = "Why?".to_string();
+    update.tags = Some(vec!["tokio".to_string()]);
+
+    let res = store
+        .update_question(update.clone(), question.id.0, &other)
+        .await;
+    assert!(matches!(res, Err(Error::Forbidden)));
+    let res = store.delete_question(question.id.0, &other).await;
+    assert!(matches!(res, Err(Error::Forbidden)));
+
+    let updated = store
+        .update_question(update.clone(), question.id.0, &owner)
+        .await
+        .unwrap();
+    assert_eq!(updated.title, "Why?");
+    assert_eq!(updated.tags, Some(vec!["tokio".to_string()]));
+    assert_eq!(updated.account_id, Some(owner.account_id));
+
+    store
+        .update_question(update, question.id.0, &moderator)
+        .await
+        .unwrap();
+    assert!(store
+        .delete_question(question.id.0, &moderator)
+        .await
+        .unwrap());
+    assert!(!contains(
+        &store
+            .get_questions(&QuestionFilter::default(), None, 0)
+            .await
+            .unwrap(),
+        &question.id
+    ));
+}
+
+async fn deleting_a_question_deletes_its_answers(store: Store) {
+    let session = session(&store, Role::User).await;
+    let question = store
+        .add_question(
+            new_question(None),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let answer = store
+        .add_answer(
+            NewAnswer {
+                content: "Like this".to_string(),
+                question_id: question.id.clone(),
+            },
+            session.account_id.clone(),
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+    assert_eq!(answer.question_id, question.id);
+    assert_eq!(answer.account_id, Some(session.account_id.clone()));
+
+    let updated = store
+        .set_answer_status(answer.id.0, ModerationStatus::Rejected)
+        .await
+        .unwrap();
+    assert_eq!(updated.status, ModerationStatus::Rejected);
+    let rejected = store
+        .get_answers_by_status(ModerationStatus::Rejected)
+        .await
+        .unwrap();
+    assert!(rejected.iter().any(|a| a.id == answer.id));
+
+    store
+        .delete_question(question.id.0, &session)
+        .await
+        .unwrap();
+    let rejected = store
+        .get_answers_by_status(ModerationStatus::Rejected)
+        .await
+        .unwrap();
+    assert!(!rejected.iter().any(|a| a.id == answer.id));
+}
+
+async fn reports_missing_content(store: Store) {
+    let session = session(&store, Role::Admin).await;
+    let question = Question {
+        id: QuestionId(i32::MAX),
+        title: "How?".to_string(),
+        content: "Please help!".to_string(),
+        tags: None,
+        account_id: None,
+        status: ModerationStatus::Published,
+        score: 0,
+        accepted_answer_id: None,
+        comment_count: 0,
+    };
+
+    let res = store.question_owner(i32::MAX).await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let res = store.update_question(question, i32::MAX, &session).await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let res = store.delete_question(i32::MAX, &session).await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let res = store
+        .set_question_status(i32::MAX, ModerationStatus::Published)
+        .await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let res = store
+        .set_answer_status(i32::MAX, ModerationStatus::Published)
+        .await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+
+    let answer = NewAnswer {
+        content: "Like this".to_string(),
+        question_id: QuestionId(i32::MAX),
+    };
+    let res = store
+        .add_answer(answer, session.account_id, ModerationStatus::Published)
+        .await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let res = store.get_answer(i32::MAX).await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+}
+
+async fn manages_accounts(store: Store) {
+    let session = session(&store, Role::User).await;
+    let account = store
+        .get_accounts()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|a| a.id == session.account_id)
+        .unwrap();
+    assert_eq!(account.role, Role::User);
+
+    let res = store
+        .add_account(Account {
+            id: None,
+            email: account.email.clone(),
+            password: "other hash".to_string(),
+            role: Role::Admin,
+            disabled: false,
+        })
+        .await;
+    assert!(matches!(res, Err(Error::AccountAlreadyExists)));
+
+    let promoted = store
+        .update_account_role(session.account_id.clone(), Role::Moderator)
+        .await
+        .unwrap();
+    assert_eq!(promoted.role, Role::Moderator);
+    let stored = store.get_account(account.email).await.unwrap();
+    assert_eq!(stored.role, Role::Moderator);
+    assert_eq!(stored.password, "hash");
+
+    let res = store.get_account("nobody@example.com".to_string()).await;
+    assert!(matches!(res, Err(Error::AccountNotFound)));
+    let res = store
+        .update_account_role(AccountId(i32::MAX), Role::Admin)
+        .await;
+    assert!(matches!(res, Err(Error::AccountNotFound)));
+}
+
+async fn edits_keep_answers_in_moderation(store: Store) {
+    let session = session(&store, Role::User).await;
+    let question = store
+        .add_question(
+            new_question(None),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let answer = store
+        .add_answer(
+            NewAnswer {
+                content: "Like this".to_string(),
+                question_id: question.id.clone(),
+            },
+            session.account_id.clone(),
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+    store
+        .set_answer_status(answer.id.0, ModerationStatus::Rejected)
+        .await
+        .unwrap();
+
+    let mut update = answer.clone();
+    update.content = "Like that".to_string();
+    update.status = ModerationStatus::Published;
+    let edited = store
+        .update_answer(update, answer.id.0, &session)
+        .await
+        .unwrap();
+    assert_eq!(edited.content, "Like that");
+    assert_eq!(edited.status, ModerationStatus::Rejected);
+    let rejected = store
+        .get_answers_by_status(ModerationStatus::Rejected)
+        .await
+        .unwrap();
+    assert!(rejected.iter().any(|a| a.id == answer.id));
+}
+
+async fn only_owners_and_moderators_change_answers(store: Store) {
+    let owner = session(&store, Role::User).await;
+    let other = session(&store, Role::User).await;
+    let moderator = session(&store, Role::Moderator).await;
+    let question = store
+        .add_question(
+            new_question(None),
+            other.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let answer = store
+        .add_answer(
+            NewAnswer {
+                content: "Like this".to_string(),
+                question_id: question.id.clone(),
+            },
+            owner.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+
+    let mut update = answer.clone();
+    update.content = "Like that".to_string();
+    update.status = ModerationStatus::Unchecked;
+
+    let res = store
+        .update_answer(update.clone(), answer.id.0, &other)
+        .await;
+    assert!(matches!(res, Err(Error::Forbidden)));
+    let res = store.delete_answer(answer.id.0, &other).await;
+    assert!(matches!(res, Err(Error::Forbidden)));
+
+    let updated = store
+        .update_answer(update.clone(), answer.id.0, &owner)
+        .await
+        .unwrap();
+    assert_eq!(updated.content, "Like that");
+    assert_eq!(updated.status, ModerationStatus::Unchecked);
+    assert_eq!(updated.question_id, question.id);
+    assert_eq!(updated.account_id, Some(owner.account_id));
+    assert_eq!(
+        store.get_answer(answer.id.0).await.unwrap().content,
+        "Like that"
+    );
+
+    assert!(store.delete_answer(answer.id.0, &moderator).await.unwrap());
+    let res = store.answer_owner(answer.id.0).await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+    let res = store.update_answer(update, answer.id.0, &moderator).await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+}
+
+async fn disables_accounts_and_resets_passwords(store: Store) {
+    let session = session(&store, Role::User).await;
+
+    let disabled = store
+        .set_account_disabled(session.account_id.clone(), true)
+        .await
+        .unwrap();
+    assert!(disabled.disabled);
+    assert!(
+        store
+            .get_account(disabled.email.clone())
+            .await
+            .unwrap()
+            .disabled
+    );
+    let profile = store
+        .get_account_profile(session.account_id.clone())
+        .await
+        .unwrap();
+    assert!(profile.disabled);
+
+    let enabled = store
+        .set_account_disabled(session.account_id.clone(), false)
+        .await
+        .unwrap();
+    assert!(!enabled.disabled);
+
+    store
+        .update_account_password(session.account_id, "new hash".to_string())
+        .await
+        .unwrap();
+    let account = store.get_account(enabled.email).await.unwrap();
+    assert_eq!(account.password, "new hash");
+    assert!(!account.disabled);
+
+    let res = store.set_account_disabled(AccountId(i32::MAX), true).await;
+    assert!(matches!(res, Err(Error::AccountNotFound)));
+    let res = store.get_account_profile(AccountId(i32::MAX)).await;
+    assert!(matches!(res, Err(Error::AccountNotFound)));
+    let res = store
+        .update_account_password(AccountId(i32::MAX), "hash".to_string())
+        .await;
+    assert!(matches!(res, Err(Error::AccountNotFound)));
+}
+
+async fn pages_through_everything_for_exports(store: Store) {
+    let session = session(&store, Role::User).await;
+    let published = store
+        .add_question(
+            new_question(Some(vec!["rust"])),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let rejected = store
+        .add_question(
+            new_question(None),
+            session.account_id.clone(),
+            ModerationStatus::Rejected,
+        )
+        .await
+        .unwrap();
+
+    // Other tests may add questions to Postgres at the same time, only ours are known
+    let page = store
+        .get_questions_after(published.id.0 - 1, i32::MAX)
+        .await
+        .unwrap();
+    assert_eq!(page[0].id, published.id);
+    assert_eq!(page[0].tags, published.tags);
+    assert!(contains(&page, &rejected.id));
+    let page = store.get_questions_after(published.id.0, 1).await.unwrap();
+    assert_eq!(page.len(), 1);
+    assert!(page[0].id.0 > published.id.0);
+
+    let mut added = Vec::new();
+    for (question, status) in [
+        (&published, ModerationStatus::Pending),
+        (&rejected, ModerationStatus::Published),
+    ] {
+        let answer = NewAnswer {
+            content: "Like this".to_string(),
+            question_id: question.id.clone(),
+        };
+        added.push(
+            store
+                .add_answer(answer, session.account_id.clone(), status)
+                .await
+                .unwrap(),
+        );
+    }
+
+    let answers = store
+        .get_answers_of_questions(vec![published.id.0, rejected.id.0])
+        .await
+        .unwrap();
+    let ids: Vec<_> = answers.iter().map(|a| a.id.clone()).collect();
+    assert_eq!(ids, vec![added[0].id.clone(), added[1].id.clone()]);
+    assert_eq!(answers[0].status, ModerationStatus::Pending);
+    assert!(store
+        .get_answers_of_questions(Vec::new())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+async fn gets_a_question_with_its_answers(store: Store) {
+    let session = session(&store, Role::User).await;
+    let question = store
+        .add_question(
+            new_question(Some(vec!["rust"])),
+            session.account_id.clone(),
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+
+    // Hidden questions are found as well, the routes decide who gets to see them
+    let found = store.get_question(question.id.0).await.unwrap();
+    assert_eq!(found.tags, question.tags);
+    assert_eq!(found.status, ModerationStatus::Pending);
+    let res = store.get_question(i32::MAX).await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+
+    let mut visible = Vec::new();
+    for status in [
+        ModerationStatus::Published,
+        ModerationStatus::Rejected,
+        ModerationStatus::Unchecked,
+        ModerationStatus::Published,
+    ] {
+        let answer = NewAnswer {
+            content: "Like this".to_string(),
+            question_id: question.id.clone(),
+        };
+        let answer = store
+            .add_answer(answer, session.account_id.clone(), status)
+            .await
+            .unwrap();
+        if status.is_visible() {
+            visible.push(answer.id);
+        }
+    }
+
+    let ids = |answers: Vec<Answer>| answers.into_iter().map(|a| a.id).collect::<Vec<_>>();
+    let answers = store
+        .get_answers(question.id.0, AnswerSort::Oldest, None, 0)
+        .await
+        .unwrap();
+    assert_eq!(ids(answers), visible);
+    let page = store
+        .get_answers(question.id.0, AnswerSort::Oldest, Some(1), 1)
+        .await
+        .unwrap();
+    assert_eq!(ids(page), visible[1..2].to_vec());
+    assert!(store
+        .get_answers(i32::MAX, AnswerSort::Oldest, None, 0)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+/// A word no other test uses, so searches only find what the test added
+fn unique_word() -> String {
+    uuid::Uuid::new_v4()
+        .simple()
+        .to_string()
+        .chars()
+        .map(|c| match c.to_digit(10) {
+            Some(digit) => (b'g' + digit as u8) as char,
+            None => c,
+        })
+        .collect()
+}
+
+async fn escapes_search_snippets(store: Store) {
+    let session = session(&store, Role::User).await;
+    let word = unique_word();
+    let question = NewQuestion {
+        title: format!("Is {} safe?", word),
+        content: format!("<script>alert(1)</script> & {} \"quoted\"", word),
+        tags: None,
+    };
+    store
+        .add_question(
+            question,
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let query = SearchQuery {
+        q: word.clone(),
+        tags: Vec::new(),
+        from: None,
+        to: None,
+    };
+
+    let hits = store.search(&query, None, 0).await.unwrap();
+    assert_eq!(hits.len(), 1);
+    let snippet = &hits[0].snippet;
+    assert!(snippet.contains(&format!("<b>{}</b>", word)), "{}", snippet);
+    assert!(!snippet.contains("<script>"), "{}", snippet);
+    assert!(
+        !snippet.replace("<b>", "").replace("</b>", "").contains('<'),
+        "{}",
+        snippet
+    );
+}
+
+async fn searches_questions_and_answers(store: Store) {
+    let session = session(&store, Role::User).await;
+    let (word, other) = (unique_word(), unique_word());
+    let add = |title: String, tags: Option<Vec<&str>>, status| {
+        let question = NewQuestion {
+            title,
+            content: format!("Nothing to see here, only {}", other),
+            tags: tags.map(|tags| tags.into_iter().map(String::from).collect()),
+        };
+        store.add_question(question, session.account_id.clone(), status)
+    };
+    let title = format!("How do {} work?", word);
+    let asked = add(title, Some(vec!["rust"]), ModerationStatus::Published)
+        .await
+        .unwrap();
+    let answered = add(
+        "Something else".to_string(),
+        None,
+        ModerationStatus::Unchecked,
+    )
+    .await
+    .unwrap();
+    let hidden = add(format!("Hidden {}", word), None, ModerationStatus::Pending)
+        .await
+        .unwrap();
+    for (question, status) in [
+        (&answered, ModerationStatus::Published),
+        (&hidden, ModerationStatus::Published),
+        (&asked, ModerationStatus::Rejected),
+    ] {
+        let answer = NewAnswer {
+            content: format!("Most {} work like this", word),
+            question_id: question.id.clone(),
+        };
+        store
+            .add_answer(answer, session.account_id.clone(), status)
+            .await
+            .unwrap();
+    }
+
+    let day = |offset: i64| (chrono::Utc::now() + chrono::Duration::days(offset)).date_naive();
+    let search = |q: String, tags: Vec<&str>, days: Option<(i64, i64)>| SearchQuery {
+        q,
+        tags: tags.into_iter().map(String::from).collect(),
+        from: days.map(|(from, _)| day(from)),
+        to: days.map(|(_, to)| day(to)),
+    };
+    let ids = |hits: Vec<SearchHit>| hits.into_iter().map(|h| h.question.id).collect::<Vec<_>>();
+
+    // A match in the question ranks above a match in one of its answers
+    let hits = store
+        .search(&search(word.clone(), vec![], None), None, 0)
+        .await
+        .unwrap();
+    assert!(hits[0].rank > hits[1].rank);
+    assert!(hits[0].snippet.contains(&format!("<b>{}</b>", word)));
+    assert!(hits[1].snippet.contains(&word));
+    assert_eq!(ids(hits), vec![asked.id.clone(), answered.id.clone()]);
+
+    let page = store
+        .search(&search(word.clone(), vec![], None), Some(1), 1)
+        .await
+        .unwrap();
+    assert_eq!(ids(page), vec![answered.id.clone()]);
+
+    // All words have to be in the question or all in one answer
+    let both = format!("{} {}", word, other);
+    let hits = store
+        .search(&search(both, vec![], None), None, 0)
+        .await
+        .unwrap();
+    assert_eq!(ids(hits), vec![asked.id.clone()]);
+
+    let hits = store
+        .search(&search(word.clone(), vec!["rust"], None), None, 0)
+        .await
+        .unwrap();
+    assert_eq!(ids(hits), vec![asked.id.clone()]);
+    let hits = store
+        .search(&search(word.clone(), vec!["rust", "warp"], None), None, 0)
+        .await
+        .unwrap();
+    assert!(hits.is_empty());
+
+    let hits = store
+        .search(&search(word.clone(), vec![], Some((-1, 1))), None, 0)
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 2);
+    let hits = store
+        .search(&search(word.clone(), vec![], Some((1, 2))), None, 0)
+        .await
+        .unwrap();
+    assert!(hits.is_empty());
+    let hits = store
+        .search(&search(word, vec![], Some((-2, -1))), None, 0)
+        .await
+        .unwrap();
+    assert!(hits.is_empty());
+}
+
+async fn filters_and_counts_tags(store: Store) {
+    let session = session(&store, Role::User).await;
+    let (first, second) = (unique_word(), unique_word());
+    let mut add = Vec::new();
+    for (tags, status) in [
+        (vec![first.as_str()], ModerationStatus::Published),
+        (
+            vec![first.as_str(), second.as_str()],
+            ModerationStatus::Unchecked,
+        ),
+        (vec![second.as_str()], ModerationStatus::Published),
+        (
+            vec![first.as_str(), second.as_str()],
+            ModerationStatus::Pending,
+        ),
+    ] {
+        let question = store
+            .add_question(new_question(Some(tags)), session.account_id.clone(), status)
+            .await
+            .unwrap();
+        add.push(question.id);
+    }
+
+    let filter = |tags: Vec<&String>, tag_match| QuestionFilter {
+        tags: tags.into_iter().cloned().collect(),
+        tag_match,
+        ..QuestionFilter::default()
+    };
+    let ids = |questions: Vec<Question>| questions.into_iter().map(|q| q.id).collect::<Vec<_>>();
+
+    // Hidden questions never match
+    let any = store
+        .get_questions(&filter(vec![&first, &second], TagMatch::Any), None, 0)
+        .await
+        .unwrap();
+    assert_eq!(ids(any), add[..3].to_vec());
+    let all = store
+        .get_questions(&filter(vec![&first, &second], TagMatch::All), None, 0)
+        .await
+        .unwrap();
+    assert_eq!(ids(all), vec![add[1].clone()]);
+    let page = store
+        .get_questions(&filter(vec![&second], TagMatch::Any), Some(1), 1)
+        .await
+        .unwrap();
+    assert_eq!(ids(page), vec![add[2].clone()]);
+
+    let tags = store.get_tags().await.unwrap();
+    let count = |tag: &String| tags.iter().find(|t| &t.tag == tag).map(|t| t.questions);
+    assert_eq!(count(&first), Some(2));
+    assert_eq!(count(&second), Some(2));
+    // The most used tags come first
+    assert!(tags
+        .windows(2)
+        .all(|pair: &[TagCount]| pair[0].questions >= pair[1].questions));
+}
+
+async fn sorts_and_filters_questions(store: Store) {
+    let other = session(&store, Role::User).await.account_id;
+    let session = session(&store, Role::User).await;
+    let mut asked = Vec::new();
+    for status in [
+        ModerationStatus::Published,
+        ModerationStatus::Published,
+        ModerationStatus::Unchecked,
+    ] {
+        let question = store
+            .add_question(new_question(None), session.account_id.clone(), status)
+            .await
+            .unwrap();
+        asked.push(question.id);
+    }
+    for (question, status) in [
+        (&asked[1], ModerationStatus::Published),
+        (&asked[1], ModerationStatus::Unchecked),
+        (&asked[0], ModerationStatus::Published),
+        (&asked[2], ModerationStatus::Pending),
+    ] {
+        let answer = NewAnswer {
+            content: "Like this".to_string(),
+            question_id: question.clone(),
+        };
+        store
+            .add_answer(answer, session.account_id.clone(), status)
+            .await
+            .unwrap();
+    }
+
+    // Only the questions of this test's account
+    let list = |change: &dyn Fn(&mut QuestionFilter)| {
+        let mut filter = QuestionFilter {
+            author: Some(session.account_id.clone()),
+            ..QuestionFilter::default()
+        };
+        change(&mut filter);
+        let store = store.clone();
+        async move {
+            let questions = store.get_questions(&filter, None, 0).await.unwrap();
+            questions.into_iter().map(|q| q.id).collect::<Vec<_>>()
+        }
+    };
+    let ids = |order: [usize; 3]| order.map(|i| asked[i].clone()).to_vec();
+
+    assert_eq!(list(&|_| {}).await, ids([0, 1, 2]));
+    assert_eq!(
+        list(&|f| f.sort = QuestionSort::Newest).await,
+        ids([2, 1, 0])
+    );
+    // Pending answers do not count
+    assert_eq!(
+        list(&|f| f.sort = QuestionSort::MostAnswered).await,
+        ids([1, 0, 2])
+    );
+    assert_eq!(
+        list(&|f| f.sort = QuestionSort::RecentlyActive).await.len(),
+        3
+    );
+    assert_eq!(list(&|f| f.unanswered = true).await, vec![asked[2].clone()]);
+
+    let day = |offset: i64| Some((chrono::Utc::now() + chrono::Duration::days(offset)).naive_utc());
+    assert_eq!(list(&|f| f.created_after = day(-1)).await.len(), 3);
+    assert!(list(&|f| f.created_after = day(1)).await.is_empty());
+    assert!(list(&|f| f.created_before = day(-1)).await.is_empty());
+
+    assert!(list(&|f| f.author = Some(other.clone())).await.is_empty());
+}
+
+async fn pages_with_cursors(store: Store) {
+    let session = session(&store, Role::User).await;
+    let mut asked = Vec::new();
+    for answers in [0, 2, 1, 2, 0] {
+        let question = store
+            .add_question(
+                new_question(None),
+                session.account_id.clone(),
+                ModerationStatus::Published,
+            )
+            .await
+            .unwrap();
+        for _ in 0..answers {
+            let answer = NewAnswer {
+                content: "Like this".to_string(),
+                question_id: question.id.clone(),
+            };
+            store
+                .add_answer(
+                    answer,
+                    session.account_id.clone(),
+                    ModerationStatus::Published,
+                )
+                .await
+                .unwrap();
+        }
+        asked.push(question.id);
+    }
+
+    // Walks through all pages of two questions of this test's account
+    let walk = |sort| {
+        let store = store.clone();
+        let filter = QuestionFilter {
+            author: Some(session.account_id.clone()),
+            sort,
+            ..QuestionFilter::default()
+        };
+        async move {
+            let mut pages = Vec::new();
+            let mut after = None;
+            loop {
+                let page = store
+                    .get_question_page(&filter, after.as_ref(), 2)
+                    .await
+                    .unwrap();
+                assert_eq!(page.has_more, page.next_cursor.is_some());
+                pages.push(page.items.into_iter().map(|q| q.id).collect::<Vec<_>>());
+                match page.next_cursor {
+                    Some(cursor) => after = Some(cursor),
+                    None => return pages,
+                }
+            }
+        }
+    };
+    let ids = |order: &[usize]| order.iter().map(|i| asked[*i].clone()).collect::<Vec<_>>();
+
+    assert_eq!(
+        walk(QuestionSort::Oldest).await,
+        vec![ids(&[0, 1]), ids(&[2, 3]), ids(&[4])]
+    );
+    assert_eq!(
+        walk(QuestionSort::Newest).await,
+        vec![ids(&[4, 3]), ids(&[2, 1]), ids(&[0])]
+    );
+    assert_eq!(
+        walk(QuestionSort::MostAnswered).await,
+        vec![ids(&[1, 3]), ids(&[2, 0]), ids(&[4])]
+    );
+
+    let filter = QuestionFilter {
+        author: Some(session.account_id.clone()),
+        sort: QuestionSort::Newest,
+        ..QuestionFilter::default()
+    };
+    assert_eq!(store.count_questions(&filter).await.unwrap(), 5);
+    // A question asked while paging does not push the next page back
+    let first = store.get_question_page(&filter, None, 2).await.unwrap();
+    store
+        .add_question(
+            new_question(None),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let second = store
+        .get_question_page(&filter, first.next_cursor.as_ref(), 2)
+        .await
+        .unwrap();
+    let second: Vec<_> = second.items.into_iter().map(|q| q.id).collect();
+    assert_eq!(second, ids(&[2, 1]));
+
+    let answers = store
+        .get_answer_page(asked[1].0, AnswerSort::Oldest, None, 1)
+        .await
+        .unwrap();
+    assert!(answers.has_more);
+    let rest = store
+        .get_answer_page(
+            asked[1].0,
+            AnswerSort::Oldest,
+            answers.next_cursor.as_ref(),
+            1,
+        )
+        .await
+        .unwrap();
+    assert!(!rest.has_more);
+    assert!(rest.items[0].id.0 > answers.items[0].id.0);
+    assert_eq!(store.count_answers(asked[1].0).await.unwrap(), 2);
+}
+
+async fn votes_on_questions_and_answers(store: Store) {
+    let session = session(&store, Role::User).await;
+    let voters = [
+        session.account_id.clone(),
+        self::session(&store, Role::User).await.account_id,
+        self::session(&store, Role::User).await.account_id,
+    ];
+    let mut asked = Vec::new();
+    for _ in 0..3 {
+        let question = store
+            .add_question(
+                new_question(None),
+                session.account_id.clone(),
+                ModerationStatus::Published,
+            )
+            .await
+            .unwrap();
+        assert_eq!(question.score, 0);
+        asked.push(question.id);
+    }
+
+    let vote = |id: &QuestionId, voter: usize, vote| {
+        store.vote_question(id.0, voters[voter].clone(), vote)
+    };
+    assert_eq!(vote(&asked[1], 0, Some(Vote::Up)).await.unwrap().score, 1);
+    assert_eq!(vote(&asked[1], 1, Some(Vote::Up)).await.unwrap().score, 2);
+    // Voting again replaces the vote of the account
+    assert_eq!(vote(&asked[1], 1, Some(Vote::Up)).await.unwrap().score, 2);
+    assert_eq!(vote(&asked[1], 1, Some(Vote::Down)).await.unwrap().score, 0);
+    assert_eq!(vote(&asked[1], 2, Some(Vote::Up)).await.unwrap().score, 1);
+    assert_eq!(vote(&asked[2], 0, Some(Vote::Up)).await.unwrap().score, 1);
+    assert_eq!(vote(&asked[2], 1, Some(Vote::Up)).await.unwrap().score, 2);
+    assert_eq!(
+        vote(&asked[0], 0, Some(Vote::Down)).await.unwrap().score,
+        -1
+    );
+    // Taking back a vote, or one that was never cast
+    assert_eq!(vote(&asked[0], 0, None).await.unwrap().score, 0);
+    assert_eq!(vote(&asked[0], 1, None).await.unwrap().score, 0);
+    assert_eq!(store.get_question(asked[2].0).await.unwrap().score, 2);
+
+    let filter = QuestionFilter {
+        author: Some(session.account_id.clone()),
+        sort: QuestionSort::MostVoted,
+        ..QuestionFilter::default()
+    };
+    let questions = store.get_questions(&filter, None, 0).await.unwrap();
+    let ids: Vec<_> = questions.into_iter().map(|q| q.id).collect();
+    assert_eq!(
+        ids,
+        vec![asked[2].clone(), asked[1].clone(), asked[0].clone()]
+    );
+    let first = store.get_question_page(&filter, None, 1).await.unwrap();
+    let rest = store
+        .get_question_page(&filter, first.next_cursor.as_ref(), 2)
+        .await
+        .unwrap();
+    let rest: Vec<_> = rest.items.into_iter().map(|q| q.id).collect();
+    assert_eq!(rest, vec![asked[1].clone(), asked[0].clone()]);
+
+    let mut answers = Vec::new();
+    for _ in 0..3 {
+        let answer = NewAnswer {
+            content: "Like this".to_string(),
+            question_id: asked[0].clone(),
+        };
+        let answer = store
+            .add_answer(
+                answer,
+                session.account_id.clone(),
+                ModerationStatus::Published,
+            )
+            .await
+            .unwrap();
+        answers.push(answer.id);
+    }
+    let voted = store
+        .vote_answer(answers[2].0, voters[0].clone(), Some(Vote::Up))
+        .await
+        .unwrap();
+    assert_eq!(voted.score, 1);
+    let voted = store
+        .vote_answer(answers[0].0, voters[0].clone(), Some(Vote::Down))
+        .await
+        .unwrap();
+    assert_eq!(voted.score, -1);
+
+    // Answers with the same score stay in the order they were given
+    let sorted = store
+        .get_answers(asked[0].0, AnswerSort::MostVoted, None, 0)
+        .await
+        .unwrap();
+    let sorted: Vec<_> = sorted.into_iter().map(|a| a.id).collect();
+    assert_eq!(
+        sorted,
+        vec![answers[2].clone(), answers[1].clone(), answers[0].clone()]
+    );
+    let first = store
+        .get_answer_page(asked[0].0, AnswerSort::MostVoted, None, 2)
+        .await
+        .unwrap();
+    assert!(first.has_more);
+    let rest = store
+        .get_answer_page(
+            asked[0].0,
+            AnswerSort::MostVoted,
+            first.next_cursor.as_ref(),
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(rest.items.len(), 1);
+    assert_eq!(rest.items[0].id, answers[0]);
+
+    let res = store
+        .vote_question(i32::MAX, voters[0].clone(), Some(Vote::Up))
+        .await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let res = store.vote_answer(i32::MAX, voters[0].clone(), None).await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+}
+
+async fn accepts_an_answer(store: Store) {
+    let session = session(&store, Role::User).await;
+    let mut asked = Vec::new();
+    for _ in 0..2 {
+        let question = store
+            .add_question(
+                new_question(None),
+                session.account_id.clone(),
+                ModerationStatus::Published,
+            )
+            .await
+            .unwrap();
+        assert_eq!(question.accepted_answer_id, None);
+        asked.push(question.id);
+    }
+    let mut answers = Vec::new();
+    for question in [&asked[0], &asked[0], &asked[0], &asked[1]] {
+        let answer = NewAnswer {
+            content: "Like this".to_string(),
+            question_id: question.clone(),
+        };
+        let answer = store
+            .add_answer(
+                answer,
+                session.account_id.clone(),
+                ModerationStatus::Published,
+            )
+            .await
+            .unwrap();
+        answers.push(answer.id);
+    }
+    let voted = store
+        .vote_answer(answers[0].0, session.account_id.clone(), Some(Vote::Up))
+        .await
+        .unwrap();
+    assert_eq!(voted.score, 1);
+
+    let accepted = store
+        .set_accepted_answer(asked[0].0, Some(answers[1].0))
+        .await
+        .unwrap();
+    assert_eq!(accepted.accepted_answer_id, Some(answers[1].clone()));
+    let stored = store.get_question(asked[0].0).await.unwrap();
+    assert_eq!(stored.accepted_answer_id, Some(answers[1].clone()));
+
+    // The accepted answer comes first in every order
+    let ids = |answers: Vec<Answer>| answers.into_iter().map(|a| a.id).collect::<Vec<_>>();
+    let oldest = store
+        .get_answers(asked[0].0, AnswerSort::Oldest, None, 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        ids(oldest),
+        vec![answers[1].clone(), answers[0].clone(), answers[2].clone()]
+    );
+    let most_voted = store
+        .get_answers(asked[0].0, AnswerSort::MostVoted, None, 0)
+        .await
+        .unwrap();
+    assert_eq!(
+        ids(most_voted),
+        vec![answers[1].clone(), answers[0].clone(), answers[2].clone()]
+    );
+    for sort in [AnswerSort::Oldest, AnswerSort::MostVoted] {
+        let first = store
+            .get_answer_page(asked[0].0, sort, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(ids(first.items), vec![answers[1].clone()]);
+        let rest = store
+            .get_answer_page(asked[0].0, sort, first.next_cursor.as_ref(), 5)
+            .await
+            .unwrap();
+        assert_eq!(
+            ids(rest.items),
+            vec![answers[0].clone(), answers[2].clone()]
+        );
+    }
+
+    // Only answers to the question can be accepted
+    let res = store
+        .set_accepted_answer(asked[0].0, Some(answers[3].0))
+        .await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+    let res = store.set_accepted_answer(asked[0].0, Some(i32::MAX)).await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+    let res = store.set_accepted_answer(i32::MAX, None).await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let stored = store.get_question(asked[0].0).await.unwrap();
+    assert_eq!(stored.accepted_answer_id, Some(answers[1].clone()));
+
+    // Deleting the accepted answer takes the acceptance back
+    store.delete_answer(answers[1].0, &session).await.unwrap();
+    let stored = store.get_question(asked[0].0).await.unwrap();
+    assert_eq!(stored.accepted_answer_id, None);
+
+    store
+        .set_accepted_answer(asked[0].0, Some(answers[2].0))
+        .await
+        .unwrap();
+    let unaccepted = store.set_accepted_answer(asked[0].0, None).await.unwrap();
+    assert_eq!(unaccepted.accepted_answer_id, None);
+}
+
+async fn comments_on_questions_and_answers(store: Store) {
+    let session = session(&store, Role::User).await;
+    let other = self::session(&store, Role::User).await;
+    let moderator = self::session(&store, Role::Moderator).await;
+    let question = store
+        .add_question(
+            new_question(None),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let answer = NewAnswer {
+        content: "Like this".to_string(),
+        question_id: question.id.clone(),
+    };
+    let answer = store
+        .add_answer(
+            answer,
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    assert_eq!(question.comment_count, 0);
+    assert_eq!(answer.comment_count, 0);
+
+    let on_question = CommentTarget::Question(question.id.clone());
+    let on_answer = CommentTarget::Answer(answer.id.clone());
+    let comment = |content: &str| NewComment {
+        content: content.to_string(),
+    };
+    let first = store
+        .add_comment(
+            comment("Which version?"),
+            on_question.clone(),
+            other.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    assert_eq!(first.target, on_question);
+    let pending = store
+        .add_comment(
+            comment("Hidden"),
+            on_question.clone(),
+            other.account_id.clone(),
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+    store
+        .add_comment(
+            comment("Thanks"),
+            on_question.clone(),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let on_the_answer = store
+        .add_comment(
+            comment("Works"),
+            on_answer.clone(),
+            other.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+
+    // Only visible comments count and get listed
+    assert_eq!(
+        store
+            .get_question(question.id.0)
+            .await
+            .unwrap()
+            .comment_count,
+        2
+    );
+    assert_eq!(
+        store.get_answer(answer.id.0).await.unwrap().comment_count,
+        1
+    );
+    let comments = store.get_comments(&on_question, None, 0).await.unwrap();
+    let contents: Vec<_> = comments.iter().map(|c| c.content.as_str()).collect();
+    assert_eq!(contents, vec!["Which version?", "Thanks"]);
+    let page = store.get_comments(&on_question, Some(1), 1).await.unwrap();
+    assert_eq!(page[0].content, "Thanks");
+    assert_eq!(
+        store.get_comments(&on_answer, None, 0).await.unwrap().len(),
+        1
+    );
+
+    let queued = store
+        .get_comments_by_status(ModerationStatus::Pending)
+        .await
+        .unwrap();
+    assert!(queued.iter().any(|c| c.id == pending.id));
+    store
+        .set_comment_status(pending.id.0, ModerationStatus::Published)
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .get_question(question.id.0)
+            .await
+            .unwrap()
+            .comment_count,
+        3
+    );
+
+    // Only the author and moderators change a comment
+    let changed = Comment {
+        content: "Which version of warp?".to_string(),
+        ..first.clone()
+    };
+    let res = store
+        .update_comment(changed.clone(), first.id.0, &session)
+        .await;
+    assert!(matches!(res, Err(Error::Forbidden)));
+    let updated = store
+        .update_comment(changed, first.id.0, &other)
+        .await
+        .unwrap();
+    assert_eq!(updated.content, "Which version of warp?");
+    assert_eq!(
+        store.get_comment(first.id.0).await.unwrap().content,
+        "Which version of warp?"
+    );
+    let hidden = Comment {
+        status: ModerationStatus::Pending,
+        ..updated
+    };
+    store
+        .update_comment(hidden, first.id.0, &moderator)
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .get_question(question.id.0)
+            .await
+            .unwrap()
+            .comment_count,
+        2
+    );
+
+    let res = store.delete_comment(pending.id.0, &session).await;
+    assert!(matches!(res, Err(Error::Forbidden)));
+    store.delete_comment(pending.id.0, &other).await.unwrap();
+    assert_eq!(
+        store
+            .get_question(question.id.0)
+            .await
+            .unwrap()
+            .comment_count,
+        1
+    );
+    assert!(matches!(
+        store.get_comment(pending.id.0).await,
+        Err(Error::CommentNotFound)
+    ));
+
+    let missing = CommentTarget::Question(QuestionId(i32::MAX));
+    let res = store
+        .add_comment(
+            comment("?"),
+            missing,
+            other.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await;
+    assert!(matches!(res, Err(Error::QuestionNotFound)));
+    let missing = CommentTarget::Answer(AnswerId(i32::MAX));
+    let res = store
+        .add_comment(
+            comment("?"),
+            missing,
+            other.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await;
+    assert!(matches!(res, Err(Error::AnswerNotFound)));
+    assert!(matches!(
+        store.comment_owner(i32::MAX).await,
+        Err(Error::CommentNotFound)
+    ));
+
+    // Comments go with the answer they are on
+    store.delete_answer(answer.id.0, &session).await.unwrap();
+    let res = store.get_comment(on_the_answer.id.0).await;
+    assert!(matches!(res, Err(Error::CommentNotFound)));
+}
+
+/// Runs every test of the suite against the store the given function opens,
+/// skipping the backend when it returns `None`
+async fn edits_keep_comments_in_moderation(store: Store) {
+    let session = session(&store, Role::User).await;
+    let question = store
+        .add_question(
+            new_question(None),
+            session.account_id.clone(),
+            ModerationStatus::Published,
+        )
+        .await
+        .unwrap();
+    let target = CommentTarget::Question(question.id.clone());
+    let comment = store
+        .add_comment(
+            NewComment {
+                content: "Which version?".to_string(),
+            },
+            target.clone(),
+            session.account_id.clone(),
+            ModerationStatus::Pending,
+        )
+        .await
+        .unwrap();
+    store
+        .set_comment_status(comment.id.0, ModerationStatus::Rejected)
+        .await
+        .unwrap();
+
+    let mut update = comment.clone();
+    update.content = "Which edition?".to_string();
+    update.status = ModerationStatus::Published;
+    let edited = store
+        .update_comment(update, comment.id.0, &session)
+        .await
+        .unwrap();
+    assert_eq!(edited.content, "Which edition?");
+    assert_eq!(edited.status, ModerationStatus::Rejected);
+    assert!(store
+        .get_comments(&target, None, 0)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        store
+            .get_question(question.id.0)
+            .await
+            .unwrap()
+            .comment_count,
+        0
+    );
+}
+
+#[tokio::test]
+async fn seeded_tags_are_normalized() {
+    let store: Store = Arc::new(
+        MemoryStore::from_legacy([LegacyQuestion {
+            id: "1".to_string(),
+            title: "First question".to_string(),
+            content: "Content of question".to_string(),
+            tags: Some(vec![
+                " Rust".to_string(),
+                "rust".to_string(),
+                "WARP".to_string(),
+            ]),
+        }])
+        .unwrap(),
+    );
+
+    let question = store.get_question(1).await.unwrap();
+    assert_eq!(
+        question.tags,
+        Some(vec!["rust".to_string(), "warp".to_string()])
+    );
+
+    let too_long = LegacyQuestion {
+        id: "1".to_string(),
+        title: "First question".to_string(),
+        content: "Content of question".to_string(),
+        tags: Some(vec!["a".repeat(100)]),
+    };
+    assert!(matches!(
+        MemoryStore::from_legacy([too_long]),
+        Err(Error::InvalidTag(_))
+    ));
+}
+
+macro_rules! suite {
+    ($backend:ident: $($test:ident),* $(,)?) => {
+        mod $backend {
+            $(
+                #[tokio::test]
+                async fn $test() {
+                    super::$test(super::$backend().await).await;
+                }
+            )*
+        }
+    };
+    (#[$attr:meta] $backend:ident: $($test:ident),* $(,)?) => {
+        mod $backend {
+            $(
+                #[tokio::test]
+                #[$attr]
+                async fn $test() {
+                    super::$test(super::$backend().await).await;
+                }
+            )*
+        }
+    };
+}
+
+macro_rules! backends {
+    ($($(#[$attr:meta])* $backend:ident),*) => {
+        $(
+            suite!($(#[$attr])* $backend:
+                adds_and_lists_questions,
+                pages_through_questions,
+                hides_questions_until_published,
+                edits_keep_questions_in_moderation,
+                only_owners_and_moderators_change_questions,
+                deleting_a_question_deletes_its_answers,
+                reports_missing_content,
+                only_owners_and_moderators_change_answers,
+                edits_keep_answers_in_moderation,
+                manages_accounts,
+                disables_accounts_and_resets_passwords,
+                pages_through_everything_for_exports,
+                gets_a_question_with_its_answers,
+                searches_questions_and_answers,
+                escapes_search_snippets,
+                filters_and_counts_tags,
+                sorts_and_filters_questions,
+                pages_with_cursors,
+                votes_on_questions_and_answers,
+                accepts_an_answer,
+                comments_on_questions_and_answers,
+                edits_keep_comments_in_moderation,
+            );
+        )*
+    };
+}
+
+backends!(
+    memory,
+    sqlite,
+    #[ignore = "needs TEST_DATABASE_URL"]
+    postgres
+);
@@ -0,0 +1,515 @@
+//! Bulk import and export of questions together with their answers and tags
+
+use crate::profanity::Moderation;
+use crate::store::memory::LegacyQuestion;
+use crate::store::Store;
+use crate::types::account::AccountId;
+use crate::types::answer::{Answer, NewAnswer};
+use crate::types::moderation::ModerationStatus;
+use crate::types::question::{NewQuestion, Question, QuestionId};
+use crate::types::tag::normalize_tags;
+use futures_util::stream::{self, Stream};
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Number of questions an export reads from the store at once
+const EXPORT_BATCH_SIZE: i32 = 100;
+
+/// Separates the tags in the `tags` column of CSV files
+const CSV_TAG_SEPARATOR: char = ';';
+
+const CSV_HEADERS: [&str; 8] = [
+    "kind",
+    "id",
+    "question_id",
+    "title",
+    "content",
+    "tags",
+    "status",
+    "account_id",
+];
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// One question per line, with its answers nested
+    #[default]
+    Jsonl,
+    /// One row per question or answer, the answers follow their question
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Jsonl => "application/x-ndjson",
+            ExportFormat::Csv => "text/csv",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            other => Err(format!("Unknown export format: {}", other)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    #[default]
+    Jsonl,
+    /// Answers refer to a question earlier in the file by its `question_id`
+    Csv,
+    /// The map of questions of `questions/questions.json`, which has no answers
+    Legacy,
+}
+
+impl FromStr for ImportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(ImportFormat::Jsonl),
+            "csv" => Ok(ImportFormat::Csv),
+            "legacy" => Ok(ImportFormat::Legacy),
+            other => Err(format!("Unknown import format: {}", other)),
+        }
+    }
+}
+
+/// A question with its answers, as a line of a JSON Lines file
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QuestionRecord {
+    /// The id in the exporting store, imported questions get new ids
+    #[serde(default)]
+    pub id: Option<i32>,
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Imported questions are published unless the record says otherwise
+    #[serde(default)]
+    pub status: Option<ModerationStatus>,
+    /// Only informative, imported content belongs to the importing account
+    #[serde(default)]
+    pub account_id: Option<i32>,
+    #[serde(default)]
+    pub answers: Vec<AnswerRecord>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnswerRecord {
+    #[serde(default)]
+    pub id: Option<i32>,
+    pub content: String,
+    #[serde(default)]
+    pub status: Option<ModerationStatus>,
+    #[serde(default)]
+    pub account_id: Option<i32>,
+}
+
+impl QuestionRecord {
+    fn new(question: Question, answers: Vec<Answer>) -> Self {
+        QuestionRecord {
+            id: Some(question.id.0),
+            title: question.title,
+            content: question.content,
+            tags: question.tags.unwrap_or_default(),
+            status: Some(question.status),
+            account_id: question.account_id.map(|id| id.0),
+            answers: answers.into_iter().map(AnswerRecord::new).collect(),
+        }
+    }
+}
+
+impl AnswerRecord {
+    fn new(answer: Answer) -> Self {
+        AnswerRecord {
+            id: Some(answer.id.0),
+            content: answer.content,
+            status: Some(answer.status),
+            account_id: answer.account_id.map(|id| id.0),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    Question,
+    Answer,
+}
+
+/// A row of a CSV file, in the order of `CSV_HEADERS`
+#[derive(Deserialize, Serialize, Debug)]
+struct CsvRow {
+    kind: Kind,
+    id: Option<i32>,
+    /// The question of an answer, as it is called in the file
+    question_id: Option<i32>,
+    #[serde(default)]
+    title: String,
+    content: String,
+    #[serde(default)]
+    tags: String,
+    status: Option<ModerationStatus>,
+    account_id: Option<i32>,
+}
+
+/// Streams every question with its answers, whatever their status,
+/// reading a batch of questions from the store at a time
+pub fn export(store: Store, format: ExportFormat) -> impl Stream<Item = Result<String, Error>> {
+    // The state is the id of the last exported question, `None` once the export is over
+    stream::unfold(Some(0), move |after_id| {
+        let store = store.clone();
+        async move {
+            let after_id = after_id?;
+            let questions = match store.get_questions_after(after_id, EXPORT_BATCH_SIZE).await {
+                Ok(questions) => questions,
+                Err(e) => return Some((Err(e), None)),
+            };
+            // CSV files start with a header, even when there is nothing to export
+            if questions.is_empty() && !(after_id == 0 && format == ExportFormat::Csv) {
+                return None;
+            }
+
+            let ids: Vec<i32> = questions.iter().map(|q| q.id.0).collect();
+            let mut answers: HashMap<i32, Vec<Answer>> = HashMap::new();
+            match store.get_answers_of_questions(ids).await {
+                Ok(all) => {
+                    for answer in all {
+                        answers
+                            .entry(answer.question_id.0)
+                            .or_default()
+                            .push(answer);
+                    }
+                }
+                Err(e) => return Some((Err(e), None)),
+            }
+
+            let next = questions.last().map(|q| q.id.0);
+            let records = questions.into_iter().map(|question| {
+                let answers = answers.remove(&question.id.0).unwrap_or_default();
+                QuestionRecord::new(question, answers)
+            });
+            let chunk = match format {
+                ExportFormat::Jsonl => to_jsonl(records),
+                ExportFormat::Csv => to_csv(records, after_id == 0),
+            };
+            Some((Ok(chunk), next))
+        }
+    })
+}
+
+fn to_jsonl(records: impl Iterator<Item = QuestionRecord>) -> String {
+    let mut chunk = String::new();
+    for record in records {
+        chunk.push_str(&serde_json::to_string(&record).expect("records serialize to JSON"));
+        chunk.push('\n');
+    }
+    chunk
+}
+
+fn to_csv(records: impl Iterator<Item = QuestionRecord>, header: bool) -> String {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+
+    if header {
+        writer
+            .write_record(CSV_HEADERS)
+            .expect("writing to memory cannot fail");
+    }
+    for record in records {
+        let tags = record.tags.join(&CSV_TAG_SEPARATOR.to_string());
+        writer
+            .serialize(CsvRow {
+                kind: Kind::Question,
+                id: record.id,
+                question_id: None,
+                title: record.title,
+                content: record.content,
+                tags,
+                status: record.status,
+                account_id: record.account_id,
+            })
+            .expect("rows serialize to CSV");
+        for answer in record.answers {
+            writer
+                .serialize(CsvRow {
+                    kind: Kind::Answer,
+                    id: answer.id,
+                    question_id: record.id,
+                    title: String::new(),
+                    content: answer.content,
+                    tags: String::new(),
+                    status: answer.status,
+                    account_id: answer.account_id,
+                })
+                .expect("rows serialize to CSV");
+        }
+    }
+
+    let bytes = writer.into_inner().expect("writing to memory cannot fail");
+    String::from_utf8(bytes).expect("CSV of strings is UTF-8")
+}
+
+/// What an import added, and the rows it had to leave out
+#[derive(Serialize, Debug, Default)]
+pub struct ImportReport {
+    pub questions: usize,
+    pub answers: usize,
+    pub errors: Vec<RowError>,
+}
+
+/// A question or answer which was not imported
+#[derive(Serialize, Debug)]
+pub struct RowError {
+    /// The line in JSON Lines and CSV files, the position among the questions otherwise
+    pub row: u64,
+    pub kind: &'static str,
+    /// The id the question or answer has in the file
+    pub id: Option<String>,
+    pub error: String,
+}
+
+/// Adds the questions and answers of a file to the store, owned by `account_id`.
+/// Rows which cannot be read or stored end up in the report, the other rows get imported.
+/// With `moderation`, the text is checked the same way as posts to the server.
+pub async fn import(
+    store: &Store,
+    moderation: Option<&Moderation>,
+    account_id: AccountId,
+    format: ImportFormat,
+    input: &str,
+) -> Result<ImportReport, Error> {
+    let mut importer = Importer {
+        store,
+        moderation,
+        account_id,
+        report: ImportReport::default(),
+        question_ids: HashMap::new(),
+    };
+    match format {
+        ImportFormat::Jsonl => importer.jsonl(input).await,
+        ImportFormat::Csv => importer.csv(input).await?,
+        ImportFormat::Legacy => importer.legacy(input).await?,
+    }
+    Ok(importer.report)
+}
+
+struct Importer<'a> {
+    store: &'a Store,
+    moderation: Option<&'a Moderation>,
+    account_id: AccountId,
+    report: ImportReport,
+    /// Ids of the questions in the file, mapped to the ids they got in the store
+    question_ids: HashMap<i32, i32>,
+}
+
+impl Importer<'_> {
+    async fn jsonl(&mut self, input: &str) {
+        for (index, line) in input.lines().enumerate() {
+            let row = index as u64 + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<QuestionRecord>(line) {
+                Ok(record) => self.question(row, record).await,
+                Err(e) => self.error(row, "question", None, e.to_string()),
+            }
+        }
+    }
+
+    async fn csv(&mut self, input: &str) -> Result<(), Error> {
+        let mut reader = csv::Reader::from_reader(input.as_bytes());
+        let headers = reader
+            .headers()
+            .map_err(|e| Error::InvalidImport(e.to_string()))?
+            .clone();
+
+        for record in reader.records() {
+            let (row, res) = match record {
+                Ok(record) => (
+                    record.position().map_or(0, |p| p.line()),
+                    record.deserialize::<CsvRow>(Some(&headers)),
+                ),
+                Err(e) => (e.position().map_or(0, |p| p.line()), Err(e)),
+            };
+            let row_data = match res {
+                Ok(row_data) => row_data,
+                Err(e) => {
+                    self.error(row, "row", None, e.to_string());
+                    continue;
+                }
+            };
+
+            match row_data.kind {
+                Kind::Question => {
+                    let tags = row_data
+                        .tags
+                        .split(CSV_TAG_SEPARATOR)
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from)
+                        .collect();
+                    let record = QuestionRecord {
+                        id: row_data.id,
+                        title: row_data.title,
+                        content: row_data.content,
+                        tags,
+                        status: row_data.status,
+                        account_id: row_data.account_id,
+                        answers: Vec::new(),
+                    };
+                    self.question(row, record).await;
+                }
+                Kind::Answer => {
+                    let id = row_data.id.map(|id| id.to_string());
+                    let question_id = match row_data
+                        .question_id
+                        .and_then(|id| self.question_ids.get(&id))
+                    {
+                        Some(question_id) => *question_id,
+                        None => {
+                            let error = match row_data.question_id {
+                                Some(id) => {
+                                    format!("Question {} was not imported from this file", id)
+                                }
+                                None => "The answer has no question_id".to_string(),
+                            };
+                            self.error(row, "answer", id, error);
+                            continue;
+                        }
+                    };
+                    let answer = AnswerRecord {
+                        id: row_data.id,
+                        content: row_data.content,
+                        status: row_data.status,
+                        account_id: row_data.account_id,
+                    };
+                    if let Err(e) = self.answer(question_id, answer).await {
+                        self.error(row, "answer", id, e.to_string());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn legacy(&mut self, input: &str) -> Result<(), Error> {
+        let questions: HashMap<String, LegacyQuestion> =
+            serde_json::from_str(input).map_err(|e| Error::InvalidImport(e.to_string()))?;
+        let mut questions: Vec<LegacyQuestion> = questions.into_values().collect();
+        // The map has no order, numeric ids keep the order they had in the old service
+        questions.sort_by(|a, b| match (a.id.parse::<i64>(), b.id.parse::<i64>()) {
+            (Ok(a), Ok(b)) => a.cmp(&b),
+            _ => a.id.cmp(&b.id),
+        });
+
+        for (index, question) in questions.into_iter().enumerate() {
+            let record = QuestionRecord {
+                id: question.id.parse().ok(),
+                title: question.title,
+                content: question.content,
+                tags: question.tags.unwrap_or_default(),
+                status: None,
+                account_id: None,
+                answers: Vec::new(),
+            };
+            let row = index as u64 + 1;
+            if let Err(e) = self.add_question(&record).await {
+                self.error(row, "question", Some(question.id), e.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Imports a question and its answers, an answer which fails does not undo the question
+    async fn question(&mut self, row: u64, record: QuestionRecord) {
+        let id = record.id.map(|id| id.to_string());
+        let question_id = match self.add_question(&record).await {
+            Ok(question_id) => question_id,
+            Err(e) => {
+                self.error(row, "question", id, e.to_string());
+                return;
+            }
+        };
+
+        for answer in record.answers {
+            let id = answer.id.map(|id| id.to_string());
+            if let Err(e) = self.answer(question_id, answer).await {
+                self.error(row, "answer", id, e.to_string());
+            }
+        }
+    }
+
+    async fn add_question(&mut self, record: &QuestionRecord) -> Result<i32, Error> {
+        let status = record.status.unwrap_or_default();
+        let (title, status) = self.moderate(record.title.clone(), status).await?;
+        let (content, status) = self.moderate(record.content.clone(), status).await?;
+        let question = NewQuestion {
+            title,
+            content,
+            tags: normalize_tags((!record.tags.is_empty()).then(|| record.tags.clone()))?,
+        };
+
+        let question = self
+            .store
+            .add_question(question, self.account_id.clone(), status)
+            .await?;
+        if let Some(id) = record.id {
+            self.question_ids.insert(id, question.id.0);
+        }
+        self.report.questions += 1;
+        Ok(question.id.0)
+    }
+
+    async fn answer(&mut self, question_id: i32, record: AnswerRecord) -> Result<(), Error> {
+        let status = record.status.unwrap_or_default();
+        let (content, status) = self.moderate(record.content, status).await?;
+        let answer = NewAnswer {
+            content,
+            question_id: QuestionId(question_id),
+        };
+        self.store
+            .add_answer(answer, self.account_id.clone(), status)
+            .await?;
+        self.report.answers += 1;
+        Ok(())
+    }
+
+    /// Checks the text with the moderation if the import asked for it,
+    /// the status of the record only gets more restrictive
+    async fn moderate(
+        &self,
+        content: String,
+        status: ModerationStatus,
+    ) -> Result<(String, ModerationStatus), Error> {
+        match self.moderation {
+            Some(moderation) => {
+                let moderated = moderation.moderate(content).await?;
+                Ok((moderated.content, status.and(moderated.status)))
+            }
+            None => Ok((content, status)),
+        }
+    }
+
+    fn error(&mut self, row: u64, kind: &'static str, id: Option<String>, error: String) {
+        self.report.errors.push(RowError {
+            row,
+            kind,
+            id,
+            error,
+        });
+    }
+}
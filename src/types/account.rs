@@ -1,10 +1,17 @@
+use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Account {
     pub id: Option<AccountId>,
     pub email: String,
     pub password: String,
+    #[serde(default)]
+    pub role: Role,
+    /// Disabled accounts cannot log in anymore
+    #[serde(default)]
+    pub disabled: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -14,4 +21,68 @@ pub struct AccountId(pub i32);
 pub struct NewAccount {
     pub email: String,
     pub password: String,
-}
\ No newline at end of file
+}
+
+/// Account data which is safe to hand out, leaves the password hash behind
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountProfile {
+    pub id: AccountId,
+    pub email: String,
+    pub role: Role,
+    pub disabled: bool,
+}
+
+/// Permission level of an account, roles further down the
+/// list include the permissions of the ones above
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    /// Can edit and delete content of every account
+    Moderator,
+    /// Can additionally manage accounts
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(Role::User),
+            "moderator" => Ok(Role::Moderator),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("Unknown role: {}", other)),
+        }
+    }
+}
+
+/// Body of the admin route which changes the role of an account
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RoleUpdate {
+    pub role: Role,
+}
+
+/// Claims carried by the PASETO token handed out on `login`,
+/// extracted by the `auth` filter on every protected route
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Session {
+    pub exp: DateTime<Utc>,
+    pub account_id: AccountId,
+    pub nbf: DateTime<Utc>,
+    #[serde(default)]
+    pub role: Role,
+}
@@ -1,5 +1,9 @@
+use crate::types::account::AccountId;
+use crate::types::moderation::ModerationStatus;
 use crate::types::question::QuestionId;
+use handle_errors::Error;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AnswerId(pub i32);
@@ -9,6 +13,18 @@ pub struct Answer {
     pub id: AnswerId,
     pub content: String,
     pub question_id: QuestionId,
+    /// The account which posted the answer, set by the server
+    #[serde(default)]
+    pub account_id: Option<AccountId>,
+    /// Set by the server, depends on the moderation policy
+    #[serde(default)]
+    pub status: ModerationStatus,
+    /// The sum of the votes, set by the server
+    #[serde(default)]
+    pub score: i32,
+    /// The number of visible comments, set by the server
+    #[serde(default)]
+    pub comment_count: i32,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -16,3 +32,59 @@ pub struct NewAnswer {
     pub content: String,
     pub question_id: QuestionId,
 }
+
+/// The part of an answer its author can change, it stays with its question
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AnswerUpdate {
+    pub content: String,
+}
+
+/// The body of `PUT /questions/{id}/accepted_answer`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct AcceptAnswer {
+    pub answer_id: AnswerId,
+}
+
+/// The order of the answers to a question, an accepted answer always comes first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AnswerSort {
+    /// In the order they were given
+    #[default]
+    Oldest,
+    /// Highest score first, answers with the same score in the order they were given
+    MostVoted,
+}
+
+impl FromStr for AnswerSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest" => Ok(AnswerSort::Oldest),
+            "most_voted" => Ok(AnswerSort::MostVoted),
+            other => Err(format!(
+                "sort must be oldest or most_voted, got '{}'",
+                other
+            )),
+        }
+    }
+}
+
+/// Extract the order of the answers from the query parameters of
+/// `/questions/{id}` and `/questions/{id}/answers`
+/// # Example query
+/// `/questions/1?sort=most_voted`
+/// # Example usage
+/// ```rust
+/// # use rust_web::types::answer::{extract_answer_sort, AnswerSort};
+/// let query = vec![("sort".to_string(), "most_voted".to_string())];
+///
+/// assert_eq!(extract_answer_sort(&query).unwrap(), AnswerSort::MostVoted);
+/// assert_eq!(extract_answer_sort(&[]).unwrap(), AnswerSort::Oldest);
+/// ```
+pub fn extract_answer_sort(params: &[(String, String)]) -> Result<AnswerSort, Error> {
+    match params.iter().rev().find(|(name, _)| name == "sort") {
+        Some((_, sort)) => sort.parse().map_err(Error::InvalidParameter),
+        None => Ok(AnswerSort::default()),
+    }
+}
@@ -0,0 +1,100 @@
+use crate::types::account::AccountId;
+use crate::types::answer::AnswerId;
+use crate::types::moderation::ModerationStatus;
+use crate::types::question::QuestionId;
+use handle_errors::Error;
+use serde::{Deserialize, Serialize};
+
+/// Longest comment in characters, anything longer belongs in an answer
+pub const MAX_COMMENT_LENGTH: usize = 600;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommentId(pub i32);
+
+/// What a comment is on, `question_id` or `answer_id` in JSON
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum CommentTarget {
+    #[serde(rename = "question_id")]
+    Question(QuestionId),
+    #[serde(rename = "answer_id")]
+    Answer(AnswerId),
+}
+
+impl CommentTarget {
+    /// The id of the question or answer
+    pub fn id(&self) -> i32 {
+        match self {
+            CommentTarget::Question(id) => id.0,
+            CommentTarget::Answer(id) => id.0,
+        }
+    }
+
+    /// The id of the question, `None` for a comment on an answer
+    pub fn question_id(&self) -> Option<i32> {
+        match self {
+            CommentTarget::Question(id) => Some(id.0),
+            CommentTarget::Answer(_) => None,
+        }
+    }
+
+    /// The id of the answer, `None` for a comment on a question
+    pub fn answer_id(&self) -> Option<i32> {
+        match self {
+            CommentTarget::Question(_) => None,
+            CommentTarget::Answer(id) => Some(id.0),
+        }
+    }
+
+    /// The error for a target which does not exist
+    pub fn not_found(&self) -> Error {
+        match self {
+            CommentTarget::Question(_) => Error::QuestionNotFound,
+            CommentTarget::Answer(_) => Error::AnswerNotFound,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Comment {
+    pub id: CommentId,
+    pub content: String,
+    #[serde(flatten)]
+    pub target: CommentTarget,
+    /// The account which wrote the comment, set by the server
+    #[serde(default)]
+    pub account_id: Option<AccountId>,
+    /// Set by the server, depends on the moderation policy
+    #[serde(default)]
+    pub status: ModerationStatus,
+}
+
+/// The body of `POST /questions/{id}/comments`, `POST /answers/{id}/comments`
+/// and `PUT /comments/{id}`
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct NewComment {
+    pub content: String,
+}
+
+/// Refuses comments which are blank or longer than `MAX_COMMENT_LENGTH`
+/// # Example usage
+/// ```rust
+/// # use rust_web::types::comment::{validate_comment, MAX_COMMENT_LENGTH};
+/// assert!(validate_comment("Which version of warp?").is_ok());
+/// assert!(validate_comment("  ").is_err());
+/// assert!(validate_comment(&"a".repeat(MAX_COMMENT_LENGTH + 1)).is_err());
+/// ```
+pub fn validate_comment(content: &str) -> Result<(), Error> {
+    if content.trim().is_empty() {
+        return Err(Error::InvalidComment(
+            "a comment cannot be empty".to_string(),
+        ));
+    }
+    let length = content.chars().count();
+    if length > MAX_COMMENT_LENGTH {
+        return Err(Error::InvalidComment(format!(
+            "a comment is at most {} characters long, this one has {}",
+            MAX_COMMENT_LENGTH, length
+        )));
+    }
+    Ok(())
+}
@@ -1,4 +1,9 @@
+pub mod account;
 pub mod answer;
+pub mod comment;
+pub mod moderation;
 pub mod pagination;
 pub mod question;
-pub mod account;
+pub mod search;
+pub mod tag;
+pub mod vote;
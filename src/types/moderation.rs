@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Whether a question or answer is visible to everybody,
+/// statuses further down the list are more restrictive
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ModerationStatus {
+    #[default]
+    Published,
+    /// Visible, but the moderation API could not be reached,
+    /// so the content has to be checked again later
+    Unchecked,
+    /// Waits for a moderator, hidden until it gets approved
+    Pending,
+    /// Turned down by a moderator, stays hidden
+    Rejected,
+}
+
+impl ModerationStatus {
+    pub const ALL: [ModerationStatus; 4] = [
+        ModerationStatus::Published,
+        ModerationStatus::Unchecked,
+        ModerationStatus::Pending,
+        ModerationStatus::Rejected,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ModerationStatus::Published => "published",
+            ModerationStatus::Unchecked => "unchecked",
+            ModerationStatus::Pending => "pending",
+            ModerationStatus::Rejected => "rejected",
+        }
+    }
+
+    /// Whether everybody gets to see content with this status
+    pub fn is_visible(&self) -> bool {
+        matches!(
+            self,
+            ModerationStatus::Published | ModerationStatus::Unchecked
+        )
+    }
+
+    /// Combines the status of the single fields of a post,
+    /// the most restrictive one wins
+    pub fn and(self, other: ModerationStatus) -> ModerationStatus {
+        self.max(other)
+    }
+
+    /// The status of edited content, whose new text got the status `checked`.
+    /// Pending and rejected content stays that way, only a moderator takes
+    /// content out of the queue, so an edit can't get around moderation
+    pub fn edited(self, checked: ModerationStatus) -> ModerationStatus {
+        match self {
+            ModerationStatus::Pending | ModerationStatus::Rejected => self,
+            _ => checked,
+        }
+    }
+}
+
+impl FromStr for ModerationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "published" => Ok(ModerationStatus::Published),
+            "unchecked" => Ok(ModerationStatus::Unchecked),
+            "pending" => Ok(ModerationStatus::Pending),
+            "rejected" => Ok(ModerationStatus::Rejected),
+            other => Err(format!("Unknown moderation status: {}", other)),
+        }
+    }
+}
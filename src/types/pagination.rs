@@ -1,52 +1,266 @@
+use crate::config::PaginationConfig;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use handle_errors::Error;
-use std::collections::HashMap;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::str::FromStr;
+
+/// The query parameters which choose the page, links to other pages replace them
+const PAGE_PARAMETERS: [&str; 3] = ["limit", "offset", "after"];
+
+/// Pagination as the query string has it, `extract_pagination` checks it
+/// and fills in the defaults
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct PaginationQuery {
+    #[serde(deserialize_with = "limit")]
+    pub limit: Option<i64>,
+    #[serde(deserialize_with = "offset")]
+    pub offset: Option<i64>,
+    /// Asks for cursor pagination, empty for the first page
+    pub after: Option<String>,
+    #[serde(deserialize_with = "total")]
+    pub total: bool,
+}
+
+/// Parses a query value, naming the parameter and the value if it does not parse
+fn parsed<'de, D: Deserializer<'de>, T: FromStr>(
+    deserializer: D,
+    name: &str,
+    expected: &str,
+) -> Result<T, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value
+        .parse()
+        .map_err(|_| D::Error::custom(format!("{} must be {}, got '{}'", name, expected, value)))
+}
+
+fn limit<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    parsed(deserializer, "limit", "a whole number").map(Some)
+}
+
+fn offset<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    parsed(deserializer, "offset", "a whole number").map(Some)
+}
+
+fn total<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    parsed(deserializer, "total", "true or false")
+}
 
 /// Pagination struct that is getting extracted
 /// from query params
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Pagination {
-    /// The index of the last item that has to be returned
-    pub limit: Option<i32>,
+    /// How many items the page has at most
+    pub limit: i32,
     /// The index of the first item that has to be returned
     pub offset: i32,
+    /// Whether `after` was given, which asks for cursor pagination and is empty for the first page
+    pub keyset: bool,
+    /// The cursor of the last item of the previous page
+    pub after: Option<Cursor>,
+    /// Whether a page of cursor pagination should count the items of all pages
+    pub total: bool,
+}
+
+impl Pagination {
+    /// Refuses cursor pagination for routes which only page with `offset`
+    pub fn offset_only(self) -> Result<Self, Error> {
+        match self.keyset {
+            true => Err(Error::InvalidParameter(
+                "this route pages with offset, not with after".to_string(),
+            )),
+            false => Ok(self),
+        }
+    }
+
+    /// Cuts the extra item off a page which was fetched with one more than `limit` items,
+    /// telling whether there are more pages
+    pub fn truncate<T>(&self, items: &mut Vec<T>) -> bool {
+        let has_more = items.len() > self.limit as usize;
+        items.truncate(self.limit as usize);
+        has_more
+    }
+
+    /// The RFC 8288 `Link` header of a page of the route at `path` with the query `params`,
+    /// linking to the next and, for offset pagination, the previous page. `None` if
+    /// there are no other pages.
+    pub fn links(
+        &self,
+        path: &str,
+        params: &[(String, String)],
+        has_more: bool,
+        next_cursor: Option<&Cursor>,
+    ) -> Option<String> {
+        let link = |page: [(&str, String); 2], rel: &str| {
+            let mut query: Vec<(&str, &str)> = params
+                .iter()
+                .filter(|(name, _)| !PAGE_PARAMETERS.contains(&name.as_str()))
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            query.extend(page.iter().map(|(name, value)| (*name, value.as_str())));
+            let query = serde_urlencoded::to_string(query).expect("pairs of strings encode");
+            format!("<{}?{}>; rel=\"{}\"", path, query, rel)
+        };
+
+        let limit = ("limit", self.limit.to_string());
+        let mut links = Vec::new();
+        if let (true, Some(cursor)) = (self.keyset, next_cursor) {
+            links.push(link([limit.clone(), ("after", cursor.encode())], "next"));
+        }
+        if !self.keyset && has_more {
+            let next = self.offset.saturating_add(self.limit);
+            links.push(link([limit.clone(), ("offset", next.to_string())], "next"));
+        }
+        if !self.keyset && self.offset > 0 {
+            let previous = (self.offset - self.limit).max(0);
+            links.push(link([limit, ("offset", previous.to_string())], "prev"));
+        }
+        (!links.is_empty()).then(|| links.join(", "))
+    }
+}
+
+/// Where a page of cursor pagination ends. Clients get it as an opaque string and pass it
+/// back as `after`, which keeps pages stable while new items get added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    /// The value the items are sorted by, unless they are sorted by id only
+    pub key: Option<i64>,
+    pub id: i32,
 }
 
-/// Extract query parameters from the `/questions` route
+impl Cursor {
+    /// The opaque form clients get
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(&(self.key, self.id)).expect("numbers serialize to JSON");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.encode())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || Error::InvalidParameter(format!("after is no cursor of this API: '{}'", s));
+        let json = URL_SAFE_NO_PAD.decode(s).map_err(|_| invalid())?;
+        let (key, id) = serde_json::from_slice(&json).map_err(|_| invalid())?;
+        Ok(Cursor { key, id })
+    }
+}
+
+/// A page of cursor pagination
+#[derive(Serialize, Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Pass it as `after` to get the next page, there is none on the last page
+    pub next_cursor: Option<Cursor>,
+    pub has_more: bool,
+    /// The number of items on all pages, when asked for with `total=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+impl<T> Page<T> {
+    /// Makes a page of `limit` items out of up to `limit + 1` rows,
+    /// the row which does not fit shows that there are more
+    pub fn new(mut rows: Vec<(T, Cursor)>, limit: i32) -> Self {
+        let has_more = rows.len() > limit.max(0) as usize;
+        rows.truncate(limit.max(0) as usize);
+        let next_cursor = match has_more {
+            true => rows.last().map(|(_, cursor)| cursor.clone()),
+            false => None,
+        };
+        Page {
+            items: rows.into_iter().map(|(item, _)| item).collect(),
+            next_cursor,
+            has_more,
+            total: None,
+        }
+    }
+}
+
+/// Extract the pagination from the query string of a list
 /// # Example query
-/// Get requests to this route can have a pagination attached so we just
-/// return the questions we need
-/// `/questions?limit=1&offset=10`
+/// Get requests to lists can have a pagination attached so we just
+/// return the items we need. Without `limit` a page has the configured default size,
+/// larger limits are cut down to the maximum, and without `offset` it starts at the first item.
+/// `after` asks for cursor pagination instead.
+/// `/questions?limit=1&offset=10` or `/questions?limit=20&after=WzMsMTdd&total=true`
 /// # Example usage
 /// ```rust
-/// let mut query = HashMap::new();
-/// query.insert("limit".to_string(), "1".to_string());
-/// query.insert("offset".to_string(), "10".to_string());
+/// # use rust_web::config::PaginationConfig;
+/// # use rust_web::types;
+/// let config = PaginationConfig {
+///     default_page_size: 20,
+///     max_page_size: 100,
+/// };
 ///
-/// let p = types::pagination::extract_pagination(query).unwrap();
-/// assert_eq!(p.limit, Some(1));
+/// let p = types::pagination::extract_pagination("limit=1&offset=10", &config).unwrap();
+/// assert_eq!(p.limit, 1);
 /// assert_eq!(p.offset, 10);
+/// assert!(!p.keyset);
+///
+/// let p = types::pagination::extract_pagination("tag=rust", &config).unwrap();
+/// assert_eq!(p.limit, 20);
+/// let p = types::pagination::extract_pagination("limit=1000", &config).unwrap();
+/// assert_eq!(p.limit, 100);
+/// assert!(types::pagination::extract_pagination("offset=-1", &config).is_err());
+///
+/// let p = types::pagination::extract_pagination("after=WzMsMTdd", &config).unwrap();
+/// assert!(p.keyset);
+/// assert_eq!(p.after.unwrap().id, 17);
 /// ```
-pub fn extract_pagination(params: HashMap<String, String>) -> Result<Pagination, Error> {
-    // Could be improved in the future
-    if params.contains_key("limit") && params.contains_key("offset") {
-        return Ok(Pagination {
-            // Takes the "limit" parameter in the query
-            // and tries to convert it to a number
-            limit: Some(
-                params
-                    .get("limit")
-                    .unwrap()
-                    .parse::<i32>()
-                    .map_err(Error::ParseError)?,
-            ),
-            // Takes the "offset" parameter in the query
-            // and tries to convert it to a number
-            offset: params
-                .get("offset")
-                .unwrap()
-                .parse::<i32>()
-                .map_err(Error::ParseError)?,
-        });
-    }
-    Err(Error::MissingParameters)
+pub fn extract_pagination(query: &str, config: &PaginationConfig) -> Result<Pagination, Error> {
+    let query: PaginationQuery =
+        serde_urlencoded::from_str(query).map_err(|e| Error::InvalidParameter(e.to_string()))?;
+    if query.after.is_some() && query.offset.is_some() {
+        return Err(Error::InvalidParameter(
+            "offset and after cannot be combined".to_string(),
+        ));
+    }
+
+    let limit = match query.limit {
+        None => config.default_page_size,
+        Some(limit) if limit < 1 => {
+            return Err(Error::InvalidParameter(format!(
+                "limit must be at least 1, got {}",
+                limit
+            )))
+        }
+        Some(limit) => limit.min(config.max_page_size as i64) as i32,
+    };
+    let offset = match query.offset {
+        None => 0,
+        Some(offset) => i32::try_from(offset)
+            .ok()
+            .filter(|offset| *offset >= 0)
+            .ok_or_else(|| {
+                Error::InvalidParameter(format!(
+                    "offset must be between 0 and {}, got {}",
+                    i32::MAX,
+                    offset
+                ))
+            })?,
+    };
+
+    Ok(Pagination {
+        limit,
+        offset,
+        keyset: query.after.is_some(),
+        after: query
+            .after
+            .as_deref()
+            .filter(|after| !after.is_empty())
+            .map(str::parse)
+            .transpose()?,
+        total: query.total,
+    })
 }
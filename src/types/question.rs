@@ -1,4 +1,11 @@
+use crate::types::account::AccountId;
+use crate::types::answer::{Answer, AnswerId};
+use crate::types::moderation::ModerationStatus;
+use crate::types::tag::{normalize_tags, TagMatch};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use handle_errors::Error;
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct NewQuestion {
@@ -7,13 +14,199 @@ pub struct NewQuestion {
     pub tags: Option<Vec<String>>,
 }
 
+/// The part of a question its author can change
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct QuestionUpdate {
+    pub title: String,
+    pub content: String,
+    pub tags: Option<Vec<String>>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Question {
     pub id: QuestionId,
     pub title: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    /// The account which created the question, set by the server
+    #[serde(default)]
+    pub account_id: Option<AccountId>,
+    /// Set by the server, depends on the moderation policy
+    #[serde(default)]
+    pub status: ModerationStatus,
+    /// The sum of the votes, set by the server
+    #[serde(default)]
+    pub score: i32,
+    /// The answer the author of the question accepted, set by the server
+    #[serde(default)]
+    pub accepted_answer_id: Option<AnswerId>,
+    /// The number of visible comments, set by the server
+    #[serde(default)]
+    pub comment_count: i32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Hash)]
 pub struct QuestionId(pub i32);
+
+/// A question along with a page of its answers
+#[derive(Serialize, Debug, Clone)]
+pub struct QuestionDetail {
+    #[serde(flatten)]
+    pub question: Question,
+    pub answers: Vec<Answer>,
+}
+
+/// Which questions `GET /questions` lists, and in which order
+#[derive(Debug, Clone, Default)]
+pub struct QuestionFilter {
+    /// Normalized tags, no tags lists questions with any or no tags
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    /// Only questions without visible answers
+    pub unanswered: bool,
+    /// Only questions of this account
+    pub author: Option<AccountId>,
+    /// Only questions asked at this moment or later
+    pub created_after: Option<NaiveDateTime>,
+    /// Only questions asked before this moment
+    pub created_before: Option<NaiveDateTime>,
+    pub sort: QuestionSort,
+}
+
+/// The order of `GET /questions`, questions which tie come in the order they were asked
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuestionSort {
+    /// In the order they were asked
+    #[default]
+    Oldest,
+    Newest,
+    /// Most visible answers first
+    MostAnswered,
+    /// Latest question or visible answer first
+    RecentlyActive,
+    /// Highest score first
+    MostVoted,
+}
+
+impl QuestionSort {
+    /// Whether the cursors of this order carry a sort value besides the id
+    pub fn has_key(&self) -> bool {
+        !matches!(self, QuestionSort::Oldest | QuestionSort::Newest)
+    }
+}
+
+impl FromStr for QuestionSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest" => Ok(QuestionSort::Oldest),
+            "newest" => Ok(QuestionSort::Newest),
+            "most_answered" => Ok(QuestionSort::MostAnswered),
+            "recently_active" => Ok(QuestionSort::RecentlyActive),
+            "most_voted" => Ok(QuestionSort::MostVoted),
+            other => Err(format!(
+                "sort must be oldest, newest, most_answered, recently_active or most_voted, got '{}'",
+                other
+            )),
+        }
+    }
+}
+
+/// Every parameter `GET /questions` knows, anything else gets refused
+const QUESTION_PARAMETERS: [&str; 11] = [
+    "tag",
+    "tag_match",
+    "unanswered",
+    "author",
+    "created_after",
+    "created_before",
+    "sort",
+    "limit",
+    "offset",
+    "after",
+    "total",
+];
+
+/// Extract the filter from the query parameters of `/questions`,
+/// which can list `tag` more than once. Pagination is left to `extract_pagination`.
+/// # Example query
+/// `/questions?tag=rust&tag=warp&tag_match=all&unanswered=true&sort=newest&created_after=2024-12-31`
+/// # Example usage
+/// ```rust
+/// # use rust_web::types;
+/// let query = vec![
+///     ("tag".to_string(), "Rust".to_string()),
+///     ("tag".to_string(), "warp".to_string()),
+///     ("tag_match".to_string(), "all".to_string()),
+///     ("unanswered".to_string(), "true".to_string()),
+///     ("sort".to_string(), "newest".to_string()),
+///     ("created_after".to_string(), "2024-12-31".to_string()),
+/// ];
+///
+/// let f = types::question::extract_question_filter(&query).unwrap();
+/// assert_eq!(f.tags, vec!["rust", "warp"]);
+/// assert_eq!(f.tag_match, types::tag::TagMatch::All);
+/// assert!(f.unanswered);
+/// assert_eq!(f.sort, types::question::QuestionSort::Newest);
+/// assert_eq!(f.created_after.unwrap().to_string(), "2024-12-31 00:00:00");
+///
+/// let sort = vec![("sort".to_string(), "best".to_string())];
+/// assert!(types::question::extract_question_filter(&sort).is_err());
+/// ```
+pub fn extract_question_filter(params: &[(String, String)]) -> Result<QuestionFilter, Error> {
+    let mut tags = Vec::new();
+    let mut filter = QuestionFilter::default();
+    for (name, value) in params {
+        match name.as_str() {
+            "tag" => tags.push(value.clone()),
+            "tag_match" => filter.tag_match = value.parse().map_err(Error::InvalidParameter)?,
+            "unanswered" => {
+                filter.unanswered = value.parse().map_err(|_| {
+                    Error::InvalidParameter(format!(
+                        "unanswered must be true or false, got '{}'",
+                        value
+                    ))
+                })?
+            }
+            "author" => {
+                let id = value.parse().map_err(|_| {
+                    Error::InvalidParameter(format!(
+                        "author must be an account id, got '{}'",
+                        value
+                    ))
+                })?;
+                filter.author = Some(AccountId(id));
+            }
+            "created_after" => filter.created_after = Some(moment(name, value)?),
+            "created_before" => filter.created_before = Some(moment(name, value)?),
+            "sort" => filter.sort = value.parse().map_err(Error::InvalidParameter)?,
+            name if QUESTION_PARAMETERS.contains(&name) => {}
+            unknown => {
+                return Err(Error::InvalidParameter(format!(
+                    "unknown parameter '{}', expected one of {}",
+                    unknown,
+                    QUESTION_PARAMETERS.join(", ")
+                )))
+            }
+        }
+    }
+
+    filter.tags = normalize_tags(Some(tags))?.unwrap_or_default();
+    Ok(filter)
+}
+
+/// A day, which starts at midnight UTC, or an RFC 3339 timestamp
+fn moment(name: &str, value: &str) -> Result<NaiveDateTime, Error> {
+    if let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(day.and_time(NaiveTime::MIN));
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|moment| moment.naive_utc())
+        .map_err(|_| {
+            Error::InvalidParameter(format!(
+                "{} must be a date like 2024-12-31 or a time like 2024-12-31T12:00:00Z, got '{}'",
+                name, value
+            ))
+        })
+}
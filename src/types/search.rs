@@ -0,0 +1,77 @@
+use crate::types::question::Question;
+use crate::types::tag::normalize_tags;
+use chrono::NaiveDate;
+use handle_errors::Error;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// What `/search` looks for, taken from the query parameters
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Words to find, `"quoted phrases"`, `or` and `-excluded` words work with Postgres
+    pub q: String,
+    /// Only questions with all of these tags
+    pub tags: Vec<String>,
+    /// Only questions asked on this day or later
+    pub from: Option<NaiveDate>,
+    /// Only questions asked on this day or earlier
+    pub to: Option<NaiveDate>,
+}
+
+/// A question which matches a search, best matches come first
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub question: Question,
+    pub rank: f32,
+    /// The matching part of the question or of one of its answers,
+    /// the words which were found are wrapped in `<b>` and `</b>`, the rest is HTML-escaped
+    pub snippet: String,
+}
+
+/// Extract the search from the query parameters of `/search`
+/// # Example query
+/// `/search?q=async+traits&tags=rust,tokio&from=2024-01-01&to=2024-06-30`
+/// # Example usage
+/// ```rust
+/// # use std::collections::HashMap;
+/// # use rust_web::types;
+/// let mut query = HashMap::new();
+/// query.insert("q".to_string(), "async traits".to_string());
+/// query.insert("tags".to_string(), "Rust, tokio".to_string());
+///
+/// let s = types::search::extract_search(&query).unwrap();
+/// assert_eq!(s.q, "async traits");
+/// assert_eq!(s.tags, vec!["rust", "tokio"]);
+/// assert_eq!(s.from, None);
+/// ```
+pub fn extract_search(params: &HashMap<String, String>) -> Result<SearchQuery, Error> {
+    let q = match params.get("q").map(|q| q.trim()) {
+        Some(q) if !q.is_empty() => q.to_string(),
+        _ => return Err(Error::MissingParameters),
+    };
+    let tags = params
+        .get("tags")
+        .map(|tags| normalize_tags(Some(tags.split(',').map(String::from).collect())))
+        .transpose()?
+        .flatten()
+        .unwrap_or_default();
+
+    Ok(SearchQuery {
+        q,
+        tags,
+        from: date(params, "from")?,
+        to: date(params, "to")?,
+    })
+}
+
+fn date(params: &HashMap<String, String>, name: &str) -> Result<Option<NaiveDate>, Error> {
+    match params.get(name) {
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| {
+                Error::InvalidParameter(format!("{} must be a date like 2024-12-31", name))
+            }),
+        None => Ok(None),
+    }
+}
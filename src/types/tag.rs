@@ -0,0 +1,66 @@
+use handle_errors::Error;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Longest tag in characters, longer ones are refused instead of cut off
+pub const MAX_TAG_LENGTH: usize = 32;
+
+/// A tag and how many visible questions carry it
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    pub tag: String,
+    pub questions: i64,
+}
+
+/// Whether a question needs one or all of the tags of a filter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TagMatch {
+    #[default]
+    Any,
+    All,
+}
+
+impl FromStr for TagMatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(TagMatch::Any),
+            "all" => Ok(TagMatch::All),
+            other => Err(format!("Unknown tag match: {}", other)),
+        }
+    }
+}
+
+/// Trims and lowercases a tag, `None` for a tag which is empty afterwards
+pub fn normalize_tag(tag: &str) -> Result<Option<String>, Error> {
+    let tag = tag.trim().to_lowercase();
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(Error::InvalidTag(format!(
+            "{} is longer than {} characters",
+            tag, MAX_TAG_LENGTH
+        )));
+    }
+    Ok((!tag.is_empty()).then_some(tag))
+}
+
+/// Normalizes the tags of a question and drops duplicates, the first one of them stays in place
+/// # Example usage
+/// ```rust
+/// # use rust_web::types;
+/// let tags = vec![" Rust".to_string(), "warp".to_string(), "rust ".to_string(), "".to_string()];
+///
+/// let tags = types::tag::normalize_tags(Some(tags)).unwrap();
+/// assert_eq!(tags, Some(vec!["rust".to_string(), "warp".to_string()]));
+/// ```
+pub fn normalize_tags(tags: Option<Vec<String>>) -> Result<Option<Vec<String>>, Error> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.unwrap_or_default() {
+        if let Some(tag) = normalize_tag(&tag)? {
+            if !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+    }
+    Ok((!normalized.is_empty()).then_some(normalized))
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// An up or down vote, `1` or `-1` in JSON
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "i8", into = "i8")]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+impl Vote {
+    /// What the vote adds to the score
+    pub fn value(self) -> i32 {
+        match self {
+            Vote::Up => 1,
+            Vote::Down => -1,
+        }
+    }
+}
+
+impl TryFrom<i8> for Vote {
+    type Error = String;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Vote::Up),
+            -1 => Ok(Vote::Down),
+            other => Err(format!("a vote is 1 or -1, got {}", other)),
+        }
+    }
+}
+
+impl From<Vote> for i8 {
+    fn from(vote: Vote) -> Self {
+        vote.value() as i8
+    }
+}
+
+/// The body of `PUT /questions/{id}/vote` and `PUT /answers/{id}/vote`
+/// # Example body
+/// ```rust
+/// # use rust_web::types::vote::{NewVote, Vote};
+/// let vote: NewVote = serde_json::from_str(r#"{"value": -1}"#).unwrap();
+/// assert_eq!(vote.value, Vote::Down);
+/// assert!(serde_json::from_str::<NewVote>(r#"{"value": 2}"#).is_err());
+/// ```
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+pub struct NewVote {
+    pub value: Vote,
+}